@@ -0,0 +1,328 @@
+use std::borrow::Cow;
+use std::fmt;
+use std::io::BufWriter;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::pattern::Pattern;
+use crate::theme::{Rgb, Theme};
+use crate::universe::Universe;
+
+pub const DEFAULT_CELL_SIZE: u32 = 4;
+pub const DEFAULT_GAP: u32 = 1;
+
+// Palette indices. Frames only ever use these three colors, so both formats
+// are written as small indexed images.
+const BACKGROUND: u8 = 0;
+const DEAD: u8 = 1;
+const ALIVE: u8 = 2;
+
+#[derive(Debug)]
+pub enum AnimationError {
+    Io(std::io::Error),
+    Gif(gif::EncodingError),
+    Png(png::EncodingError),
+    UnknownFormat(String),
+    /// Frames of a `width` by `height` cell crop don't fit the format.
+    TooLarge {
+        width: u32,
+        height: u32,
+        cell_size: u32,
+    },
+    NoFrames,
+}
+
+impl fmt::Display for AnimationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AnimationError::Io(e) => write!(f, "{e}"),
+            AnimationError::Gif(e) => write!(f, "couldn't encode GIF: {e}"),
+            AnimationError::Png(e) => write!(f, "couldn't encode APNG: {e}"),
+            AnimationError::UnknownFormat(ext) => {
+                write!(
+                    f,
+                    "unknown animation format '{ext}', expected .gif, .png or .apng"
+                )
+            }
+            AnimationError::TooLarge {
+                width,
+                height,
+                cell_size,
+            } => {
+                write!(
+                    f,
+                    "frames of {width}x{height} cells at {cell_size} px are too large, \
+                     use a smaller crop or cell size"
+                )
+            }
+            AnimationError::NoFrames => write!(f, "no generations to export"),
+        }
+    }
+}
+
+impl From<std::io::Error> for AnimationError {
+    fn from(e: std::io::Error) -> Self {
+        AnimationError::Io(e)
+    }
+}
+
+/// Region of the board to export, in cells.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Crop {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl FromStr for Crop {
+    type Err = String;
+
+    // `X,Y,WIDTH,HEIGHT`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<u32> = s
+            .split(',')
+            .map(|n| n.trim().parse())
+            .collect::<Result<_, _>>()
+            .map_err(|_| format!("invalid crop '{s}': expected X,Y,WIDTH,HEIGHT"))?;
+        match parts[..] {
+            [x, y, width, height] if width > 0 && height > 0 => Ok(Crop {
+                x,
+                y,
+                width,
+                height,
+            }),
+            _ => Err(format!("invalid crop '{s}': expected X,Y,WIDTH,HEIGHT")),
+        }
+    }
+}
+
+/// How cells are drawn into frames.
+#[derive(Clone, Copy, Debug)]
+pub struct Style {
+    /// Size of a cell in pixels, gap included.
+    pub cell_size: u32,
+    /// Pixels left empty on the right and bottom of each cell.
+    pub gap: u32,
+    pub alive: Rgb,
+    pub dead: Rgb,
+    pub background: Rgb,
+}
+
+impl Style {
+    pub fn new(theme: &Theme) -> Self {
+        Self {
+            cell_size: DEFAULT_CELL_SIZE,
+            gap: DEFAULT_GAP,
+            alive: theme.alive,
+            dead: theme.dead,
+            background: theme.background,
+        }
+    }
+}
+
+/// Which generations become frames: `from`, `from + step`, ... up to `to`.
+#[derive(Clone, Copy, Debug)]
+pub struct FrameRange {
+    pub from: u64,
+    pub to: u64,
+    pub step: u64,
+}
+
+/// Live cells of a range of generations, ready to be encoded.
+pub struct Animation {
+    board: (u32, u32),
+    frames: Vec<Vec<(u32, u32)>>,
+}
+
+impl Animation {
+    /// Advances `universe` through `range`, keeping every selected generation.
+    /// Generations before the universe's current one are skipped.
+    pub fn record(universe: &mut Universe, range: FrameRange) -> Self {
+        let step = range.step.max(1);
+        let mut frames = Vec::new();
+        while universe.generation() <= range.to {
+            let generation = universe.generation();
+            if generation >= range.from && (generation - range.from).is_multiple_of(step) {
+                frames.push(Pattern::snapshot(universe).cells);
+            }
+            if generation == range.to {
+                break;
+            }
+            universe.tick();
+        }
+        Self {
+            board: (universe.width(), universe.height()),
+            frames,
+        }
+    }
+
+    pub fn frame_count(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Smallest crop holding every live cell of every frame plus a one cell
+    /// margin, or the whole board when no cell is ever alive.
+    pub fn fit_crop(&self) -> Crop {
+        let mut cells = self.frames.iter().flatten();
+        let Some(&(x, y)) = cells.next() else {
+            return Crop {
+                x: 0,
+                y: 0,
+                width: self.board.0,
+                height: self.board.1,
+            };
+        };
+        let (mut min_x, mut min_y, mut max_x, mut max_y) = (x, y, x, y);
+        for &(x, y) in cells {
+            min_x = min_x.min(x);
+            min_y = min_y.min(y);
+            max_x = max_x.max(x);
+            max_y = max_y.max(y);
+        }
+        let (min_x, min_y) = (min_x.saturating_sub(1), min_y.saturating_sub(1));
+        let max_x = (max_x + 1).min(self.board.0 - 1);
+        let max_y = (max_y + 1).min(self.board.1 - 1);
+        Crop {
+            x: min_x,
+            y: min_y,
+            width: max_x - min_x + 1,
+            height: max_y - min_y + 1,
+        }
+    }
+
+    /// Writes the frames to `path`, as a GIF or an APNG depending on the
+    /// extension, waiting `delay_ms` between frames and looping forever.
+    pub fn save(
+        &self,
+        path: &Path,
+        crop: Crop,
+        style: &Style,
+        delay_ms: u32,
+    ) -> Result<(), AnimationError> {
+        if self.frames.is_empty() {
+            return Err(AnimationError::NoFrames);
+        }
+        let extension = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .unwrap_or_default()
+            .to_lowercase();
+        let too_large = || AnimationError::TooLarge {
+            width: crop.width,
+            height: crop.height,
+            cell_size: style.cell_size,
+        };
+        let size = frame_size(crop, style).ok_or_else(too_large)?;
+        match extension.as_str() {
+            "gif" => {
+                // GIF dimensions are 16 bits.
+                if size.0 > u16::MAX as u32 || size.1 > u16::MAX as u32 {
+                    return Err(too_large());
+                }
+                self.save_gif(path, crop, style, size, delay_ms)
+            }
+            "png" | "apng" => self.save_apng(path, crop, style, size, delay_ms),
+            _ => Err(AnimationError::UnknownFormat(extension)),
+        }
+    }
+
+    fn save_gif(
+        &self,
+        path: &Path,
+        crop: Crop,
+        style: &Style,
+        (width, height): (u32, u32),
+        delay_ms: u32,
+    ) -> Result<(), AnimationError> {
+        let (w, h) = (width as u16, height as u16);
+        let file = BufWriter::new(std::fs::File::create(path)?);
+        let mut encoder =
+            gif::Encoder::new(file, w, h, &palette(style)).map_err(AnimationError::Gif)?;
+        encoder
+            .set_repeat(gif::Repeat::Infinite)
+            .map_err(AnimationError::Gif)?;
+        // GIF delays are in hundredths of a second.
+        let delay = (delay_ms / 10).min(u16::MAX as u32) as u16;
+        for cells in &self.frames {
+            let frame = gif::Frame {
+                width: w,
+                height: h,
+                delay,
+                buffer: Cow::Owned(self.rasterize(cells, crop, style, width)),
+                ..gif::Frame::default()
+            };
+            encoder.write_frame(&frame).map_err(AnimationError::Gif)?;
+        }
+        Ok(())
+    }
+
+    fn save_apng(
+        &self,
+        path: &Path,
+        crop: Crop,
+        style: &Style,
+        (width, height): (u32, u32),
+        delay_ms: u32,
+    ) -> Result<(), AnimationError> {
+        let file = BufWriter::new(std::fs::File::create(path)?);
+        let mut encoder = png::Encoder::new(file, width, height);
+        encoder.set_color(png::ColorType::Indexed);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.set_palette(palette(style).to_vec());
+        let result = (|| {
+            encoder.set_animated(self.frames.len() as u32, 0)?;
+            encoder.set_frame_delay(delay_ms.min(u16::MAX as u32) as u16, 1000)?;
+            let mut writer = encoder.write_header()?;
+            for cells in &self.frames {
+                writer.write_image_data(&self.rasterize(cells, crop, style, width))?;
+            }
+            writer.finish()
+        })();
+        result.map_err(AnimationError::Png)
+    }
+
+    /// Draws one frame `width` pixels wide as palette indices, one byte per
+    /// pixel. The frame size must have been checked by `frame_size`.
+    fn rasterize(&self, cells: &[(u32, u32)], crop: Crop, style: &Style, width: u32) -> Vec<u8> {
+        let height = crop.height * style.cell_size;
+        let mut pixels = vec![BACKGROUND; width as usize * height as usize];
+        let filled = style.cell_size - style.gap.min(style.cell_size - 1);
+        let mut fill = |cx: u32, cy: u32, color: u8| {
+            for py in cy * style.cell_size..cy * style.cell_size + filled {
+                let row = (py * width) as usize;
+                let start = row + (cx * style.cell_size) as usize;
+                pixels[start..start + filled as usize].fill(color);
+            }
+        };
+
+        // Parts of the crop outside the board stay background.
+        let visible_width = self.board.0.saturating_sub(crop.x).min(crop.width);
+        let visible_height = self.board.1.saturating_sub(crop.y).min(crop.height);
+        for cy in 0..visible_height {
+            for cx in 0..visible_width {
+                fill(cx, cy, DEAD);
+            }
+        }
+        for &(x, y) in cells {
+            if x >= crop.x && y >= crop.y && x < crop.x + crop.width && y < crop.y + crop.height {
+                fill(x - crop.x, y - crop.y, ALIVE);
+            }
+        }
+        pixels
+    }
+}
+
+/// Frame size in pixels, or `None` when it or its pixel count overflows.
+fn frame_size(crop: Crop, style: &Style) -> Option<(u32, u32)> {
+    let width = crop.width.checked_mul(style.cell_size)?;
+    let height = crop.height.checked_mul(style.cell_size)?;
+    width.checked_mul(height)?;
+    Some((width, height))
+}
+
+fn palette(style: &Style) -> [u8; 9] {
+    let ([br, bg, bb], [dr, dg, db], [ar, ag, ab]) =
+        (style.background.0, style.dead.0, style.alive.0);
+    [br, bg, bb, dr, dg, db, ar, ag, ab]
+}
@@ -0,0 +1,117 @@
+use serde::{Deserialize, Serialize};
+
+use crate::math::transpose;
+
+pub const INITIAL_ZOOM: f32 = 15.0;
+pub const MIN_ZOOM: f32 = 0.25;
+pub const MAX_ZOOM: f32 = 200.0;
+
+/// 2D camera over the board. World units are cells: cell `(x, y)` covers
+/// `[x, x + 1) x [y, y + 1)`. `position` is the world point shown at the
+/// top-left corner of the window and `zoom` is the size of a cell in pixels.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct Camera {
+    pub position: [f32; 2],
+    pub zoom: f32,
+}
+
+impl std::default::Default for Camera {
+    fn default() -> Self {
+        Self {
+            position: [-0.5, -0.5],
+            zoom: INITIAL_ZOOM,
+        }
+    }
+}
+
+impl Camera {
+    /// World to window-pixel transform, column-major for the shader.
+    pub fn view_matrix(&self) -> [f32; 16] {
+        let [x, y] = self.position;
+        let z = self.zoom;
+        #[rustfmt::skip]
+        let view = [
+            z,   0.0, 0.0, -x * z,
+            0.0, z,   0.0, -y * z,
+            0.0, 0.0, 1.0,  0.0,
+            0.0, 0.0, 0.0,  1.0,
+        ];
+        transpose(view)
+    }
+
+    pub fn screen_to_world(&self, screen: [f32; 2]) -> [f32; 2] {
+        [
+            self.position[0] + screen[0] / self.zoom,
+            self.position[1] + screen[1] / self.zoom,
+        ]
+    }
+
+    /// Cell under a window position, if it lies inside a `width` x `height` board.
+    pub fn pick(&self, screen: [f32; 2], width: u32, height: u32) -> Option<(u32, u32)> {
+        let [x, y] = self.screen_to_world(screen);
+        let (x, y) = (x.floor(), y.floor());
+        if x < 0.0 || y < 0.0 || x >= width as f32 || y >= height as f32 {
+            return None;
+        }
+        Some((x as u32, y as u32))
+    }
+
+    /// Cells at least partly visible in a window of `viewport` pixels, as
+    /// `(x, y, width, height)` clipped to a `width` x `height` board.
+    pub fn visible_cells(
+        &self,
+        viewport: (u32, u32),
+        width: u32,
+        height: u32,
+    ) -> Option<(u32, u32, u32, u32)> {
+        let [x0, y0] = self.screen_to_world([0.0, 0.0]);
+        let [x1, y1] = self.screen_to_world([viewport.0 as f32, viewport.1 as f32]);
+        let (x0, y0) = (x0.floor().max(0.0) as u32, y0.floor().max(0.0) as u32);
+        let (x1, y1) = (
+            x1.ceil().min(width as f32) as u32,
+            y1.ceil().min(height as f32) as u32,
+        );
+        (x0 < x1 && y0 < y1).then_some((x0, y0, x1 - x0, y1 - y0))
+    }
+
+    pub fn pan(&mut self, screen_delta: [f32; 2]) {
+        self.position[0] -= screen_delta[0] / self.zoom;
+        self.position[1] -= screen_delta[1] / self.zoom;
+    }
+
+    /// Multiplies the zoom by `factor`, keeping the world point under
+    /// `anchor` (in window pixels) fixed on screen.
+    pub fn zoom_at(&mut self, anchor: [f32; 2], factor: f32) {
+        let world = self.screen_to_world(anchor);
+        self.zoom = (self.zoom * factor).clamp(MIN_ZOOM, MAX_ZOOM);
+        self.position = [
+            world[0] - anchor[0] / self.zoom,
+            world[1] - anchor[1] / self.zoom,
+        ];
+    }
+
+    /// Moves the camera so `world` is at the center of a window of
+    /// `viewport` pixels.
+    pub fn center_on(&mut self, world: [f32; 2], viewport: (u32, u32)) {
+        self.position = [
+            world[0] - viewport.0 as f32 / 2.0 / self.zoom,
+            world[1] - viewport.1 as f32 / 2.0 / self.zoom,
+        ];
+    }
+
+    /// Frames the world rectangle `(x, y, width, height)` in a window of
+    /// `viewport` pixels, leaving a small margin around it.
+    pub fn fit(&mut self, rect: (u32, u32, u32, u32), viewport: (u32, u32)) {
+        let (x, y, w, h) = rect;
+        let (w, h) = (w.max(1) as f32 + 2.0, h.max(1) as f32 + 2.0);
+        self.zoom = (viewport.0 as f32 / w)
+            .min(viewport.1 as f32 / h)
+            .clamp(MIN_ZOOM, MAX_ZOOM);
+
+        let center = [x as f32 + (w - 2.0) / 2.0, y as f32 + (h - 2.0) / 2.0];
+        self.position = [
+            center[0] - viewport.0 as f32 / 2.0 / self.zoom,
+            center[1] - viewport.1 as f32 / 2.0 / self.zoom,
+        ];
+    }
+}
@@ -1,17 +1,33 @@
-use crate::math::transpose;
-
-#[repr(u32)]
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum CellState {
-    Dead = 0,
+    Dead,
     Alive,
+    /// State `n` of a Generations rule, from 2 up to one less than the rule's
+    /// state count. Dying cells age one state per generation until they die,
+    /// and don't count as live neighbors meanwhile.
+    Dying(u8),
 }
 
 impl CellState {
     pub fn is_alive(&self) -> bool {
+        matches!(self, CellState::Alive)
+    }
+
+    /// State number as used by rulestrings, multi-state RLE and the shaders:
+    /// 0 for dead, 1 for alive, then the dying states.
+    pub fn index(self) -> u8 {
         match self {
-            CellState::Alive => true,
-            _ => false,
+            CellState::Dead => 0,
+            CellState::Alive => 1,
+            CellState::Dying(n) => n,
+        }
+    }
+
+    pub fn from_index(index: u8) -> Self {
+        match index {
+            0 => CellState::Dead,
+            1 => CellState::Alive,
+            n => CellState::Dying(n),
         }
     }
 }
@@ -20,8 +36,8 @@ impl std::ops::Not for CellState {
     type Output = Self;
     fn not(self) -> Self::Output {
         match self {
-            CellState::Alive => CellState::Dead,
             CellState::Dead => CellState::Alive,
+            _ => CellState::Dead,
         }
     }
 }
@@ -31,6 +47,9 @@ pub struct Cell {
     pub x: u32,
     pub y: u32,
     pub state: CellState,
+    /// Generation the cell entered its current state in, or `None` for dead
+    /// cells that haven't been alive since the board was cleared.
+    pub changed_at: Option<u64>,
 }
 
 impl std::default::Default for Cell {
@@ -39,30 +58,7 @@ impl std::default::Default for Cell {
             x: 0,
             y: 0,
             state: CellState::Dead,
-        }
-    }
-}
-
-#[repr(C)]
-#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
-pub struct CellInstance {
-    pub model: [f32; 16],
-    pub state: u32,
-}
-
-impl CellInstance {
-    pub fn from_cell(cell: &Cell, scale_factor: f32, offset: f32) -> Self {
-        #[rustfmt::skip]
-        let model = [
-            scale_factor, 0.0,          0.0, ((scale_factor + offset) * cell.x as f32) + scale_factor / 2.0 + 6.0, // 4.0 => little offset to center the grid
-            0.0,          scale_factor, 0.0, ((scale_factor + offset) * cell.y as f32) + scale_factor / 2.0 + 6.0,
-            0.0,          0.0,          1.0, 0.0,
-            0.0,          0.0,          0.0, 1.0,
-        ];
-
-        Self {
-            model: transpose(model),
-            state: cell.state as u32,
+            changed_at: None,
         }
     }
 }
@@ -0,0 +1,382 @@
+use std::path::PathBuf;
+
+use clap::{Args, Parser, Subcommand};
+
+use crate::animation::{Crop, DEFAULT_CELL_SIZE, DEFAULT_GAP};
+use crate::pattern::{Pattern, PatternError};
+use crate::rule::Rule;
+use crate::state::{GRID_COLUMN_SIZE, GRID_LINE_SIZE};
+use crate::svg::DEFAULT_SVG_CELL_SIZE;
+use crate::theme::{Coloring, Rgb, ThemePreset};
+use crate::universe::{Backend, Topology, Universe};
+
+pub const WIN_WIDTH: u32 = 610;
+pub const WIN_HEIGHT: u32 = 610;
+/// Empty cells kept around a pattern when the board grows to fit it.
+pub const PATTERN_MARGIN: u32 = 10;
+
+#[derive(Parser)]
+#[command(
+    version,
+    about = "Conway's Game of Life",
+    args_conflicts_with_subcommands = true
+)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    #[command(flatten)]
+    pub gui: GuiArgs,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Simulate a pattern without opening a window
+    Run(RunArgs),
+    /// Export a range of generations as an animated GIF or APNG
+    Animate(AnimateArgs),
+    /// Render a run frame by frame, as numbered PNGs or raw RGBA on stdout
+    Frames(FramesArgs),
+    /// Export a pattern as an SVG figure
+    Svg(SvgArgs),
+}
+
+/// Options shared by every mode that builds a universe.
+#[derive(Args)]
+pub struct UniverseArgs {
+    /// Rule in B/S notation, e.g. B3/S23, B2-a/S12 with Hensel letters,
+    /// B2/S/C3 for a Generations rule or R5,C0,M1,S34..58,B34..45,NM for a
+    /// Larger than Life rule;
+    /// overrides the one in the pattern header
+    /// [default: B3/S23]
+    #[arg(long)]
+    pub rule: Option<Rule>,
+
+    /// Board width in cells [default: 100, or wide enough for the pattern
+    /// and a margin]
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    pub width: Option<u32>,
+
+    /// Board height in cells [default: 100, or tall enough for the pattern
+    /// and a margin]
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    pub height: Option<u32>,
+
+    /// What happens to cells at the board edges
+    #[arg(long, value_enum, default_value_t = Topology::Bounded)]
+    pub topology: Topology,
+}
+
+impl UniverseArgs {
+    /// Builds an empty universe, taking the pattern's rule unless one was
+    /// given on the command line.
+    pub fn universe(&self, pattern_rule: Option<Rule>) -> Universe {
+        let width = self.width.unwrap_or(GRID_LINE_SIZE as u32);
+        let height = self.height.unwrap_or(GRID_COLUMN_SIZE as u32);
+        self.build(width, height, pattern_rule)
+    }
+
+    /// Builds a universe with `pattern` in the middle, taking its rule, then
+    /// `fallback_rule`, unless one was given on the command line. A size that
+    /// wasn't given grows from the default to fit the pattern with
+    /// `PATTERN_MARGIN` cells around it; one that was given must fit it.
+    pub fn universe_with(
+        &self,
+        pattern: &Pattern,
+        fallback_rule: Option<Rule>,
+    ) -> Result<Universe, PatternError> {
+        let fit = |given: Option<u32>, default: usize, size: u32| {
+            given.unwrap_or_else(|| (default as u32).max(size.saturating_add(2 * PATTERN_MARGIN)))
+        };
+        let width = fit(self.width, GRID_LINE_SIZE, pattern.width);
+        let height = fit(self.height, GRID_COLUMN_SIZE, pattern.height);
+        if pattern.width > width || pattern.height > height {
+            return Err(PatternError::DoesNotFit {
+                pattern: (pattern.width, pattern.height),
+                board: (width, height),
+            });
+        }
+        let mut universe = self.build(width, height, pattern.rule.or(fallback_rule));
+        pattern.place_centered(&mut universe);
+        Ok(universe)
+    }
+
+    fn build(&self, width: u32, height: u32, pattern_rule: Option<Rule>) -> Universe {
+        let rule = self.rule.or(pattern_rule).unwrap_or_default();
+        let mut universe = Universe::new(width, height, rule);
+        universe.topology = self.topology;
+        universe
+    }
+}
+
+#[derive(Args)]
+pub struct GuiArgs {
+    /// Pattern to open at startup (.rle, or plaintext .cells)
+    #[arg(short, long, value_name = "FILE")]
+    pub pattern: Option<PathBuf>,
+
+    /// Session file to reopen, as saved from the "Session" window
+    #[arg(long, value_name = "FILE", conflicts_with = "pattern")]
+    pub session: Option<PathBuf>,
+
+    #[command(flatten)]
+    pub universe: UniverseArgs,
+
+    /// Where generations are computed
+    #[arg(long, value_enum, default_value_t = Backend::Cpu)]
+    pub backend: Backend,
+
+    /// Generations per second [default: from settings, or 12]
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    pub speed: Option<u32>,
+
+    /// Start the simulation immediately instead of paused
+    #[arg(long)]
+    pub run: bool,
+
+    /// Window width in pixels [default: from settings, or 610]
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    pub window_width: Option<u32>,
+
+    /// Window height in pixels [default: from settings, or 610]
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    pub window_height: Option<u32>,
+}
+
+#[derive(Args)]
+pub struct RunArgs {
+    /// Pattern to simulate (.rle, or plaintext .cells)
+    #[arg(long = "in", value_name = "FILE")]
+    pub input: PathBuf,
+
+    /// Where to write the resulting pattern [default: stdout]
+    #[arg(long, value_name = "FILE")]
+    pub out: Option<PathBuf>,
+
+    /// Where to write the run statistics [default: stdout, or stderr when the
+    /// pattern itself goes to stdout]
+    #[arg(long, value_name = "FILE")]
+    pub summary: Option<PathBuf>,
+
+    #[command(flatten)]
+    pub universe: UniverseArgs,
+
+    /// Maximum number of generations to simulate
+    #[arg(long, default_value_t = 1000)]
+    pub gens: u64,
+
+    /// Keep simulating after the pattern dies out or starts repeating. With
+    /// the GPU backend this also avoids reading the board back every
+    /// generation
+    #[arg(long)]
+    pub no_stop: bool,
+
+    /// Where generations are computed
+    #[arg(long, value_enum, default_value_t = Backend::Cpu)]
+    pub backend: Backend,
+
+    /// Use wgpu's software adapter for the GPU backend and --png
+    #[arg(long)]
+    pub fallback_adapter: bool,
+
+    /// Also run the CPU engine and fail if the GPU backend ever produces a
+    /// different generation
+    #[arg(long)]
+    pub verify: bool,
+
+    /// Also render the final generation to a PNG image, framed around the
+    /// live cells
+    #[arg(long, value_name = "FILE")]
+    pub png: Option<PathBuf>,
+
+    /// Width of the --png image in pixels
+    #[arg(long, default_value_t = 800)]
+    pub png_width: u32,
+
+    /// Height of the --png image in pixels
+    #[arg(long, default_value_t = 800)]
+    pub png_height: u32,
+
+    /// Color theme [default: the one in the settings file]
+    #[arg(long, value_enum)]
+    pub theme: Option<ThemePreset>,
+
+    /// How cells are colored [default: the one in the settings file]
+    #[arg(long, value_enum)]
+    pub coloring: Option<Coloring>,
+}
+
+#[derive(Args)]
+pub struct AnimateArgs {
+    /// Pattern to simulate (.rle, or plaintext .cells)
+    #[arg(long = "in", value_name = "FILE")]
+    pub input: PathBuf,
+
+    /// Where to write the animation: .gif for a GIF, .png or .apng for an APNG
+    #[arg(long, value_name = "FILE")]
+    pub out: PathBuf,
+
+    #[command(flatten)]
+    pub universe: UniverseArgs,
+
+    /// First generation to export
+    #[arg(long, default_value_t = 0)]
+    pub from: u64,
+
+    /// Last generation to export
+    #[arg(long, default_value_t = 100)]
+    pub to: u64,
+
+    /// Export every STEP-th generation
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u64).range(1..))]
+    pub step: u64,
+
+    /// Delay between frames in milliseconds
+    #[arg(long, default_value_t = 100)]
+    pub delay: u32,
+
+    /// Size of a cell in pixels, gap included
+    #[arg(long, default_value_t = DEFAULT_CELL_SIZE, value_parser = clap::value_parser!(u32).range(1..))]
+    pub cell_size: u32,
+
+    /// Empty pixels between neighboring cells
+    #[arg(long, default_value_t = DEFAULT_GAP)]
+    pub gap: u32,
+
+    /// Region to export in cells, as X,Y,WIDTH,HEIGHT [default: the live
+    /// cells of every frame]
+    #[arg(long)]
+    pub crop: Option<Crop>,
+
+    /// Color theme [default: the one in the settings file]
+    #[arg(long, value_enum)]
+    pub theme: Option<ThemePreset>,
+
+    /// Override the theme's live cell color
+    #[arg(long, value_name = "RRGGBB")]
+    pub alive_color: Option<Rgb>,
+
+    /// Override the theme's dead cell color
+    #[arg(long, value_name = "RRGGBB")]
+    pub dead_color: Option<Rgb>,
+
+    /// Override the theme's background color, shown between cells
+    #[arg(long, value_name = "RRGGBB")]
+    pub background_color: Option<Rgb>,
+}
+
+#[derive(Args)]
+pub struct FramesArgs {
+    /// Pattern to simulate (.rle, or plaintext .cells)
+    #[arg(long = "in", value_name = "FILE")]
+    pub input: PathBuf,
+
+    /// Directory to write frame-00000.png, frame-00001.png, ... into
+    #[arg(long, value_name = "DIR", required_unless_present = "pipe")]
+    pub out_dir: Option<PathBuf>,
+
+    /// Write raw RGBA frames to stdout instead, e.g. for ffmpeg
+    #[arg(long, conflicts_with = "out_dir")]
+    pub pipe: bool,
+
+    #[command(flatten)]
+    pub universe: UniverseArgs,
+
+    /// Number of generations to simulate
+    #[arg(long, default_value_t = 1000)]
+    pub gens: u64,
+
+    /// Generations between consecutive frames
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..))]
+    pub per_frame: u32,
+
+    /// Frame width in pixels
+    #[arg(long, default_value_t = 1280)]
+    pub frame_width: u32,
+
+    /// Frame height in pixels
+    #[arg(long, default_value_t = 720)]
+    pub frame_height: u32,
+
+    /// Region of the board to frame, in cells, as X,Y,WIDTH,HEIGHT
+    /// [default: the whole board]
+    #[arg(long)]
+    pub crop: Option<Crop>,
+
+    /// Where generations are computed
+    #[arg(long, value_enum, default_value_t = Backend::Cpu)]
+    pub backend: Backend,
+
+    /// Use wgpu's software adapter
+    #[arg(long)]
+    pub fallback_adapter: bool,
+
+    /// Color theme [default: the one in the settings file]
+    #[arg(long, value_enum)]
+    pub theme: Option<ThemePreset>,
+
+    /// How cells are colored [default: the one in the settings file]
+    #[arg(long, value_enum)]
+    pub coloring: Option<Coloring>,
+
+    /// Let dead cells fade out over this many generations
+    #[arg(long, value_name = "GENERATIONS")]
+    pub trails: Option<u32>,
+
+    /// Overlay a heat map of how often cells were alive, averaged over about
+    /// this many generations
+    #[arg(long, value_name = "GENERATIONS", value_parser = clap::value_parser!(u32).range(1..))]
+    pub heat_map: Option<u32>,
+}
+
+#[derive(Args)]
+pub struct SvgArgs {
+    /// Pattern to draw (.rle, or plaintext .cells)
+    #[arg(long = "in", value_name = "FILE")]
+    pub input: PathBuf,
+
+    /// Where to write the SVG [default: stdout]
+    #[arg(long, value_name = "FILE")]
+    pub out: Option<PathBuf>,
+
+    /// Part of the pattern to draw, in cells, as X,Y,WIDTH,HEIGHT
+    /// [default: the whole pattern]
+    #[arg(long)]
+    pub crop: Option<Crop>,
+
+    /// Display size of a cell in pixels
+    #[arg(long, default_value_t = DEFAULT_SVG_CELL_SIZE)]
+    pub cell_size: u32,
+
+    /// Draw one rect per cell instead of merging horizontal runs
+    #[arg(long)]
+    pub no_merge: bool,
+
+    /// Draw grid lines between cells
+    #[arg(long)]
+    pub grid: bool,
+
+    /// Document title [default: the pattern name]
+    #[arg(long)]
+    pub title: Option<String>,
+
+    /// Color theme [default: the one in the settings file]
+    #[arg(long, value_enum)]
+    pub theme: Option<ThemePreset>,
+
+    /// Override the theme's live cell color
+    #[arg(long, value_name = "RRGGBB")]
+    pub alive_color: Option<Rgb>,
+
+    /// Override the theme's background color
+    #[arg(long, value_name = "RRGGBB")]
+    pub background_color: Option<Rgb>,
+
+    /// Leave the background transparent
+    #[arg(long, conflicts_with = "background_color")]
+    pub transparent: bool,
+
+    /// Override the theme's grid line color
+    #[arg(long, value_name = "RRGGBB")]
+    pub grid_color: Option<Rgb>,
+}
@@ -0,0 +1,257 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::cli::{WIN_HEIGHT, WIN_WIDTH};
+use crate::keymap::{InputAction, KeyCombo};
+use crate::rule::Rule;
+use crate::state::{INITIAL_CELL_GAP, TICK_PER_SEC};
+use crate::theme::{Coloring, Theme};
+
+const CONFIG_DIR: &str = "game-of-life";
+const CONFIG_FILE: &str = "config.toml";
+
+/// User preferences persisted between launches. Missing keys fall back to
+/// their defaults, so older files keep loading as new settings are added.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub display: DisplayConfig,
+    pub simulation: SimulationConfig,
+    pub window: WindowConfig,
+    pub confirm: ConfirmConfig,
+    /// Keys for the actions whose default shortcuts should be replaced, e.g.
+    /// `toggle-running = ["Space", "Enter"]`. An empty list unbinds an action.
+    pub keys: BTreeMap<InputAction, Vec<KeyCombo>>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DisplayConfig {
+    pub theme: Theme,
+    /// Fraction of each cell left empty between neighbors.
+    pub cell_gap: f32,
+    pub coloring: Coloring,
+    /// Generations until a live cell is drawn fully in the alive color when
+    /// coloring by age.
+    pub age_span: u32,
+    /// Generations recently dead cells take to fade out, 0 for no trails.
+    pub trail_length: u32,
+    pub heat_map: bool,
+    /// Roughly how many generations the heat map averages over.
+    pub heat_window: u32,
+    pub grid: bool,
+    pub rulers: bool,
+}
+
+impl std::default::Default for DisplayConfig {
+    fn default() -> Self {
+        Self {
+            theme: Theme::default(),
+            cell_gap: INITIAL_CELL_GAP,
+            coloring: Coloring::State,
+            age_span: 100,
+            trail_length: 0,
+            heat_map: false,
+            heat_window: 100,
+            grid: false,
+            rulers: false,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SimulationConfig {
+    pub rule: Rule,
+    pub speed: u32,
+    /// Drawing on the board is allowed while the simulation runs.
+    pub edit_while_running: bool,
+}
+
+impl std::default::Default for SimulationConfig {
+    fn default() -> Self {
+        Self {
+            rule: Rule::conway(),
+            speed: TICK_PER_SEC,
+            edit_while_running: true,
+        }
+    }
+}
+
+/// Which destructive actions ask for confirmation first.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConfirmConfig {
+    /// Clearing a board with live cells on it.
+    pub clear: bool,
+    /// Loading a session over unsaved changes.
+    pub load: bool,
+    /// Quitting with unsaved changes.
+    pub quit: bool,
+    /// Randomly filling more than [`LARGE_RANDOM_FILL`] cells.
+    pub random_fill: bool,
+}
+
+/// Cells a random fill can replace without asking first.
+pub const LARGE_RANDOM_FILL: u64 = 100 * 100;
+
+impl std::default::Default for ConfirmConfig {
+    fn default() -> Self {
+        Self {
+            clear: true,
+            load: true,
+            quit: true,
+            random_fill: true,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WindowConfig {
+    pub width: u32,
+    pub height: u32,
+    pub x: Option<i32>,
+    pub y: Option<i32>,
+}
+
+impl std::default::Default for WindowConfig {
+    fn default() -> Self {
+        Self {
+            width: WIN_WIDTH,
+            height: WIN_HEIGHT,
+            x: None,
+            y: None,
+        }
+    }
+}
+
+/// The `[keys]` table, skipping unknown actions and keys with a warning. An
+/// action whose keys are all invalid keeps its defaults.
+fn parse_keys(keys: toml::Value) -> BTreeMap<InputAction, Vec<KeyCombo>> {
+    let mut bindings = BTreeMap::new();
+    let toml::Value::Table(table) = keys else {
+        log::warn!("ignoring [keys]: expected a table of actions");
+        return bindings;
+    };
+    for (name, value) in table {
+        let action: InputAction = match toml::Value::String(name.clone()).try_into() {
+            Ok(action) => action,
+            Err(_) => {
+                log::warn!("ignoring key binding for unknown action '{name}'");
+                continue;
+            }
+        };
+        let Some(list) = value.as_array() else {
+            log::warn!("ignoring key binding for '{name}': expected a list of keys");
+            continue;
+        };
+        let mut combos = Vec::new();
+        for key in list {
+            match key.as_str().map(str::parse::<KeyCombo>) {
+                Some(Ok(combo)) => combos.push(combo),
+                Some(Err(e)) => log::warn!("ignoring key binding for '{name}': {e}"),
+                None => log::warn!("ignoring key binding for '{name}': {key} is not a string"),
+            }
+        }
+        if combos.is_empty() && !list.is_empty() {
+            continue;
+        }
+        bindings.insert(action, combos);
+    }
+    bindings
+}
+
+impl Config {
+    pub fn path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join(CONFIG_DIR).join(CONFIG_FILE))
+    }
+
+    /// Reads the config file, falling back to defaults when it is missing or
+    /// can't be parsed.
+    pub fn load() -> Self {
+        Self::try_load().unwrap_or_else(|e| {
+            log::warn!("ignoring {e}");
+            Self::default()
+        })
+    }
+
+    /// Reads the config file, with defaults when there is none yet. Fails
+    /// when the file exists but can't be read or parsed, so callers can
+    /// avoid overwriting it.
+    pub fn try_load() -> Result<Self, String> {
+        let Some(path) = Self::path() else {
+            return Ok(Self::default());
+        };
+        let text = match std::fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(format!("unreadable config {}: {e}", path.display())),
+        };
+        Self::parse(&text).map_err(|e| format!("invalid config {}: {e}", path.display()))
+    }
+
+    /// Parses a config file. Key bindings are read one by one, so a
+    /// misspelled key only loses that binding rather than every setting.
+    fn parse(text: &str) -> Result<Self, toml::de::Error> {
+        let mut table: toml::Table = toml::from_str(text)?;
+        let keys = table.remove("keys");
+        let mut config: Config = toml::Value::Table(table).try_into()?;
+        if let Some(keys) = keys {
+            config.keys = parse_keys(keys);
+        }
+        Ok(config)
+    }
+
+    pub fn save(&self) {
+        let Some(path) = Self::path() else {
+            log::warn!("no config directory available, settings not saved");
+            return;
+        };
+        let text = toml::to_string_pretty(self).unwrap();
+        let result = std::fs::create_dir_all(path.parent().unwrap())
+            .and_then(|_| std::fs::write(&path, text));
+        match result {
+            Ok(()) => log::info!("settings saved to {}", path.display()),
+            Err(e) => log::warn!("couldn't save settings to {}: {e}", path.display()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys(toml: &str) -> BTreeMap<InputAction, Vec<KeyCombo>> {
+        parse_keys(toml.parse::<toml::Table>().unwrap().into())
+    }
+
+    #[test]
+    fn bad_bindings_are_skipped() {
+        let bindings = keys(
+            r#"
+            toggle-running = ["Enter"]
+            step = ["Banana", "Period"]
+            clear = []
+            zoom-in = ["Hyper+Q"]
+            fly = ["F"]
+            pause = "P"
+            "#,
+        );
+        let combo = |s: &str| s.parse::<KeyCombo>().unwrap();
+        assert_eq!(bindings[&InputAction::ToggleRunning], [combo("Enter")]);
+        assert_eq!(bindings[&InputAction::Step], [combo("Period")]);
+        assert_eq!(bindings[&InputAction::Clear], []);
+        // Every key was bad, so the defaults stay.
+        assert!(!bindings.contains_key(&InputAction::ZoomIn));
+        assert!(!bindings.contains_key(&InputAction::Pause));
+        assert_eq!(bindings.len(), 3);
+    }
+
+    #[test]
+    fn keys_must_be_a_table() {
+        assert!(parse_keys(toml::Value::String("Space".into())).is_empty());
+    }
+}
@@ -1,22 +1,22 @@
-mod instance;
+mod board;
+pub mod compute;
+pub mod offscreen;
 mod pipeline;
 mod uniform;
 mod vertex;
 
 use egui_winit::winit;
 
-use crate::{
-    cell::{Cell, CellInstance},
-    gui::GuiCtx,
-    state::{GRID_COLUMN_SIZE, GRID_LINE_SIZE},
-};
-use vertex::{VertexBuffer, INDICES};
-use wgpu::{Device, Queue, Surface, SurfaceCapabilities, SurfaceConfiguration};
+use crate::{camera::Camera, config::DisplayConfig, gui::GuiCtx, rule::Rule, universe::Universe};
+use vertex::VertexBuffer;
+use wgpu::{Device, Queue, Surface, SurfaceConfiguration};
 
 use self::{
-    instance::{init_cell_instances, InstanceBuffers},
+    board::{init_board, BoardBuffers},
+    compute::LifeCompute,
+    offscreen::Image,
     pipeline::{init_pipeline, Pipeline},
-    uniform::init_uniforms,
+    uniform::{init_uniforms, CameraUniform, UniformBuffers},
     vertex::init_buffers,
 };
 
@@ -25,22 +25,23 @@ pub struct Gpu {
     queue: Queue,
     surface: Surface,
 
-    square_pipeline: Pipeline,
+    board_pipeline: Pipeline,
 
     square_buffers: VertexBuffer,
-    instance_buffers: InstanceBuffers,
+    board: BoardBuffers,
+    uniform_buffers: UniformBuffers,
+    life: LifeCompute,
 
     surface_config: SurfaceConfiguration,
-    pub surface_caps: SurfaceCapabilities,
 }
 
 impl Gpu {
     pub async fn new(
         window: &winit::window::Window,
-        cells: &[Cell],
-        scale_factor: f32,
-        offset: f32,
-    ) -> Self {
+        universe: &Universe,
+        display: &DisplayConfig,
+        camera: &Camera,
+    ) -> Result<Self, String> {
         let dimensions = window.inner_size();
 
         let instance = wgpu::Instance::new(wgpu::InstanceDescriptor::default());
@@ -73,56 +74,148 @@ impl Gpu {
         surface.configure(&device, &surface_config);
 
         let square_buffers = init_buffers(&device);
-        let instance_buffers = init_cell_instances(&device, cells, scale_factor, offset);
-        let uniform_buffers = init_uniforms(&device, dimensions);
-        let square_pipeline = init_pipeline(&device, &surface_config, &uniform_buffers);
+        let life = LifeCompute::new(&device, universe)?;
+        let uniform_buffers = init_uniforms(&device, dimensions, camera);
+        let board_pipeline = init_pipeline(&device, surface_config.format, &uniform_buffers);
+        let board = init_board(
+            &device,
+            &board_pipeline.bindgroups.board_layout,
+            &life,
+            surface_config.format,
+            display,
+        );
 
-        Self {
+        Ok(Self {
             device,
             surface,
             queue,
-            surface_caps,
             surface_config,
 
-            square_pipeline,
+            board_pipeline,
             square_buffers,
-            instance_buffers,
-        }
+            board,
+            uniform_buffers,
+            life,
+        })
     }
 
     pub fn device(&self) -> &Device {
         &self.device
     }
 
+    /// Whether a `width` x `height` board under `rule` fits the device, which
+    /// has to be checked before `update_cells` gets to reallocate for it.
+    pub fn check_board(&self, width: u32, height: u32, rule: &Rule) -> Result<(), String> {
+        compute::check_limits(&self.device.limits(), width, height, rule)
+    }
+
     pub fn surface_config(&self) -> &SurfaceConfiguration {
         &self.surface_config
     }
 
     pub fn resize(&mut self, dimensions: winit::dpi::PhysicalSize<u32>) {
+        // Minimized windows report a zero size, which the surface rejects.
+        if dimensions.width == 0 || dimensions.height == 0 {
+            return;
+        }
         self.surface_config.width = dimensions.width;
         self.surface_config.height = dimensions.height;
         self.surface.configure(&self.device, &self.surface_config);
     }
 
-    pub fn update_cells(&self, cells: &[Cell], scale_factor: f32, offset: f32) {
-        let instance_data: Vec<CellInstance> = cells
-            .iter()
-            .map(|c| CellInstance::from_cell(c, scale_factor, offset))
-            .collect();
+    pub fn update_camera(&self, camera: &Camera) {
+        let dimensions =
+            winit::dpi::PhysicalSize::new(self.surface_config.width, self.surface_config.height);
+        let uniform = CameraUniform::new(dimensions, camera);
         self.queue.write_buffer(
-            &self.instance_buffers.cells,
+            &self.uniform_buffers.camera,
             0,
-            bytemuck::cast_slice(&instance_data),
+            bytemuck::bytes_of(&uniform),
         )
     }
 
-    pub fn render(
+    /// Uploads the rows of the board that changed on the CPU, reallocating the
+    /// GPU buffers when the board size changed, the heat map was switched or
+    /// the rule started or stopped needing row sums.
+    pub fn update_cells(&mut self, universe: &mut Universe, display: &DisplayConfig) {
+        let dirty_rows = universe.take_dirty_rows();
+        if universe.width() != self.life.width()
+            || universe.height() != self.life.height()
+            || universe.heat().is_some() != self.life.has_heat()
+            || universe.rule.is_nearest() == self.life.has_row_sums()
+        {
+            self.life = LifeCompute::new(&self.device, universe)
+                .expect("board size checked against the GPU limits");
+            self.board = init_board(
+                &self.device,
+                &self.board_pipeline.bindgroups.board_layout,
+                &self.life,
+                self.surface_config.format,
+                display,
+            );
+        }
+        self.life.write_params(&self.queue, universe);
+        self.update_display(display);
+        for rows in dirty_rows {
+            self.life.upload_rows(&self.queue, universe, rows);
+        }
+    }
+
+    /// Updates the cell gap and colors without touching cell states.
+    pub fn update_display(&mut self, display: &DisplayConfig) {
+        if *display != self.board.display || self.board.states != self.life.rule_states() {
+            self.board.write_display(&self.queue, &self.life, display);
+        }
+    }
+
+    /// Advances the board on the GPU, leaving the CPU copy untouched.
+    pub fn step_cells(&mut self, universe: &Universe, generations: u32) {
+        self.life.write_params(&self.queue, universe);
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+        self.life.step(&mut encoder, generations);
+        self.queue.submit(std::iter::once(encoder.finish()));
+    }
+
+    pub fn download_cells(&self, universe: &mut Universe) {
+        self.life.download(&self.device, &self.queue, universe);
+    }
+
+    /// Renders the current view offscreen at `scale` times the window size.
+    pub fn screenshot(
         &self,
-        gui: &mut GuiCtx,
-        build_output: egui::FullOutput,
-        clear_color: wgpu::Color,
-    ) {
-        let output = self.surface.get_current_texture().unwrap();
+        camera: &Camera,
+        scale: u32,
+        display: &DisplayConfig,
+    ) -> Result<Image, String> {
+        let size = (
+            self.surface_config.width * scale,
+            self.surface_config.height * scale,
+        );
+        let camera = Camera {
+            zoom: camera.zoom * scale as f32,
+            ..*camera
+        };
+        offscreen::render(
+            &self.device,
+            &self.queue,
+            &self.life,
+            &camera,
+            size,
+            display,
+        )
+    }
+
+    pub fn render(&self, gui: &mut GuiCtx, build_output: egui::FullOutput) {
+        let output = match self.surface.get_current_texture() {
+            Ok(output) => output,
+            Err(wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated) => {
+                self.surface.configure(&self.device, &self.surface_config);
+                return;
+            }
+            Err(e) => panic!("couldn't acquire the next frame: {e}"),
+        };
 
         let view = output
             .texture
@@ -140,28 +233,18 @@ impl Gpu {
                     view: &view,
                     resolve_target: None,
                     ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(clear_color),
+                        load: wgpu::LoadOp::Clear(self.board.clear_color()),
                         store: true,
                     },
                 })],
                 depth_stencil_attachment: None,
             });
 
-            pass.set_pipeline(&self.square_pipeline.pipeline);
-            pass.set_bind_group(0, &self.square_pipeline.bindgroups.projection_mat, &[]);
-
-            pass.set_vertex_buffer(0, self.square_buffers.vertex.slice(..));
-            pass.set_index_buffer(
-                self.square_buffers.index.slice(..),
-                wgpu::IndexFormat::Uint32,
-            );
-
-            pass.set_vertex_buffer(1, self.instance_buffers.cells.slice(..));
-
-            pass.draw_indexed(
-                0..INDICES.len() as u32,
-                0,
-                0..(GRID_COLUMN_SIZE * GRID_LINE_SIZE) as _,
+            self.board.draw(
+                &mut pass,
+                &self.board_pipeline,
+                &self.square_buffers,
+                &self.life,
             );
 
             gui.renderer()
@@ -172,3 +255,42 @@ impl Gpu {
         output.present();
     }
 }
+
+/// Device without any window or surface, for running the simulation from the
+/// command line. `force_fallback_adapter` picks wgpu's software adapter so
+/// this works on machines without a GPU.
+pub async fn headless_device(force_fallback_adapter: bool) -> Result<(Device, Queue), String> {
+    let instance = wgpu::Instance::new(wgpu::InstanceDescriptor::default());
+    let adapter = instance
+        .request_adapter(&wgpu::RequestAdapterOptions {
+            force_fallback_adapter,
+            ..Default::default()
+        })
+        .await
+        .ok_or("no suitable GPU adapter found")?;
+
+    let info = adapter.get_info();
+    log::info!("using adapter {} ({:?})", info.name, info.backend);
+
+    let downlevel = adapter.get_downlevel_capabilities();
+    if !downlevel
+        .flags
+        .contains(wgpu::DownlevelFlags::COMPUTE_SHADERS)
+    {
+        return Err(format!(
+            "adapter {} has no compute shader support",
+            info.name
+        ));
+    }
+
+    adapter
+        .request_device(
+            &wgpu::DeviceDescriptor {
+                limits: adapter.limits(),
+                ..Default::default()
+            },
+            None,
+        )
+        .await
+        .map_err(|e| e.to_string())
+}
@@ -0,0 +1,172 @@
+use wgpu::{
+    util::DeviceExt, BindGroup, BindGroupLayout, Buffer, BufferUsages, Device, Queue, TextureFormat,
+};
+
+use super::{compute::LifeCompute, pipeline::Pipeline, vertex::VertexBuffer, vertex::INDICES};
+use crate::{
+    config::DisplayConfig,
+    theme::{Coloring, Rgb},
+};
+
+#[repr(C)]
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct BoardUniform {
+    width: u32,
+    height: u32,
+    gap: f32,
+    coloring: u32,
+    age_span: u32,
+    trail_length: u32,
+    heat_map: u32,
+    grid: u32,
+    states: u32,
+    _pad: [u32; 3],
+    alive: [f32; 4],
+    young: [f32; 4],
+    dying: [f32; 4],
+    dead: [f32; 4],
+    heat: [f32; 4],
+    grid_color: [f32; 4],
+    background: [f32; 4],
+}
+
+impl BoardUniform {
+    fn new(life: &LifeCompute, display: &DisplayConfig, srgb: bool) -> Self {
+        Self {
+            width: life.width(),
+            height: life.height(),
+            gap: display.cell_gap,
+            coloring: match display.coloring {
+                Coloring::State => 0,
+                Coloring::Age => 1,
+            },
+            age_span: display.age_span.max(1),
+            trail_length: display.trail_length,
+            // Only what the simulation actually computes can be shown.
+            heat_map: (display.heat_map && life.has_heat()) as u32,
+            grid: display.grid as u32,
+            states: life.rule_states(),
+            _pad: [0; 3],
+            alive: shader_color(display.theme.alive, srgb),
+            young: shader_color(display.theme.young, srgb),
+            dying: shader_color(display.theme.dying, srgb),
+            dead: shader_color(display.theme.dead, srgb),
+            heat: shader_color(display.theme.heat, srgb),
+            grid_color: shader_color(display.theme.grid, srgb),
+            background: shader_color(display.theme.background, srgb),
+        }
+    }
+}
+
+/// `color` as the shader should output it. sRGB targets encode on write, so
+/// they expect linear values; other targets store the channels as they are.
+fn shader_color(color: Rgb, srgb: bool) -> [f32; 4] {
+    let [r, g, b] = match srgb {
+        true => color.to_linear(),
+        false => color.0.map(|c| c as f32 / 255.0),
+    };
+    [r, g, b, 1.0]
+}
+
+/// What the board shader needs besides the camera: the board size, the cell
+/// gap, the colors and one bind group per simulation state buffer, so drawing
+/// always reads whichever one holds the latest generation.
+pub struct BoardBuffers {
+    uniform: Buffer,
+    pub bind_groups: [BindGroup; 2],
+    pub display: DisplayConfig,
+    /// Rule state count the dying colors were spread over.
+    pub states: u32,
+    srgb: bool,
+}
+
+impl BoardBuffers {
+    pub fn write_display(&mut self, queue: &Queue, life: &LifeCompute, display: &DisplayConfig) {
+        let uniform = BoardUniform::new(life, display, self.srgb);
+        queue.write_buffer(&self.uniform, 0, bytemuck::bytes_of(&uniform));
+        self.display = display.clone();
+        self.states = life.rule_states();
+    }
+
+    /// The theme's background, for clearing the target before drawing.
+    pub fn clear_color(&self) -> wgpu::Color {
+        let [r, g, b, a] = shader_color(self.display.theme.background, self.srgb);
+        wgpu::Color {
+            r: r as f64,
+            g: g as f64,
+            b: b as f64,
+            a: a as f64,
+        }
+    }
+
+    /// Records the draw call for the whole board, reading the latest
+    /// generation from `life`.
+    pub fn draw<'a>(
+        &'a self,
+        pass: &mut wgpu::RenderPass<'a>,
+        pipeline: &'a Pipeline,
+        square: &'a VertexBuffer,
+        life: &LifeCompute,
+    ) {
+        pass.set_pipeline(&pipeline.pipeline);
+        pass.set_bind_group(0, &pipeline.bindgroups.camera, &[]);
+        pass.set_bind_group(1, &self.bind_groups[life.current_index()], &[]);
+
+        pass.set_vertex_buffer(0, square.vertex.slice(..));
+        pass.set_index_buffer(square.index.slice(..), wgpu::IndexFormat::Uint32);
+
+        pass.draw_indexed(0..INDICES.len() as u32, 0, 0..1);
+    }
+}
+
+pub fn init_board(
+    device: &Device,
+    layout: &BindGroupLayout,
+    life: &LifeCompute,
+    format: TextureFormat,
+    display: &DisplayConfig,
+) -> BoardBuffers {
+    let srgb = format.describe().srgb;
+    let uniform = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: None,
+        contents: bytemuck::bytes_of(&BoardUniform::new(life, display, srgb)),
+        usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+    });
+
+    let bind_groups = [0, 1].map(|i| {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: uniform.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: life.states()[i].as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: life.changed()[i].as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: life.clock().as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: life.heat()[i].as_entire_binding(),
+                },
+            ],
+        })
+    });
+
+    BoardBuffers {
+        uniform,
+        bind_groups,
+        display: display.clone(),
+        states: life.rule_states(),
+        srgb,
+    }
+}
@@ -0,0 +1,603 @@
+use std::ops::Range;
+
+use wgpu::{
+    include_wgsl, util::DeviceExt, BindGroup, BindGroupLayout, Buffer, BufferUsages,
+    ComputePipeline, Device, Queue, ShaderStages,
+};
+
+use crate::cell::CellState;
+use crate::rule::{Neighborhood, Rule, COUNT_WORDS};
+use crate::universe::{Topology, Universe};
+
+const WORKGROUP_SIZE: u32 = 64;
+const MAX_WORKGROUPS_PER_DIMENSION: u32 = 65535;
+/// Change generation of dead cells that have never been alive.
+const NEVER: u32 = u32::MAX;
+
+#[repr(C)]
+#[derive(Copy, Clone, PartialEq, Eq, bytemuck::Pod, bytemuck::Zeroable)]
+struct Params {
+    width: u32,
+    height: u32,
+    torus: u32,
+    /// 0 when the heat map is off.
+    heat_window: u32,
+    /// Cell states of the rule, above 2 for Generations rules.
+    states: u32,
+    range: u32,
+    neighborhood: u32,
+    middle: u32,
+    birth: [u32; COUNT_WORDS],
+    survival: [u32; COUNT_WORDS],
+    /// Birth and survival by arrangement of the nearest neighbors, used
+    /// instead of the counts when only those count.
+    birth_arrangements: [u32; 8],
+    survival_arrangements: [u32; 8],
+}
+
+impl Params {
+    fn new(universe: &Universe) -> Self {
+        let rule = &universe.rule;
+        let arrangements = rule.arrangements();
+        Self {
+            width: universe.width(),
+            height: universe.height(),
+            torus: (universe.topology == Topology::Torus) as u32,
+            heat_window: universe.heat_window().unwrap_or(0),
+            states: rule.states,
+            range: rule.range,
+            neighborhood: match rule.neighborhood {
+                Neighborhood::Moore => 0,
+                Neighborhood::VonNeumann => 1,
+                Neighborhood::Circular => 2,
+            },
+            middle: rule.middle as u32,
+            birth: rule.birth.0,
+            survival: rule.survival.0,
+            birth_arrangements: arrangements.birth.0,
+            survival_arrangements: arrangements.survival.0,
+        }
+    }
+}
+
+/// Runs the simulation in a compute shader. The board lives in two storage
+/// buffers holding one byte per cell, packed four to a `u32`; each step reads
+/// one and writes the other, and `current()` is always the latest generation.
+/// The renderer reads the same buffers, so cells never travel through the CPU
+/// just to be drawn.
+///
+/// Cell ages are kept the same way as on the CPU: a second pair of buffers
+/// holds the generation each cell last changed in, and `clock` holds the
+/// current generation, so cells that don't change are never written.
+///
+/// The heat map gets a third pair of buffers, one `f32` per cell, while the
+/// universe has one. Otherwise they are single placeholder words.
+///
+/// Larger than Life rules add a buffer of prefix sums along each row, filled
+/// by a pass before every step, so a neighborhood is counted with two reads
+/// per row instead of one per cell. Rules that only look at the 8 nearest
+/// neighbors get a placeholder word instead.
+pub struct LifeCompute {
+    pipeline: ComputePipeline,
+    clock_pipeline: ComputePipeline,
+    row_sums_pipeline: ComputePipeline,
+    params: Buffer,
+    /// Last contents written to `params`, to skip redundant writes.
+    written_params: Params,
+    states: [Buffer; 2],
+    changed: [Buffer; 2],
+    clock: Buffer,
+    heat: [Buffer; 2],
+    has_heat: bool,
+    /// Only used through the bind groups, held to make its lifetime explicit.
+    _row_sums: Buffer,
+    has_row_sums: bool,
+    /// Generation in `clock` once the submitted work is done.
+    generation: u64,
+    bind_groups: [BindGroup; 2],
+    current: usize,
+    width: u32,
+    height: u32,
+}
+
+fn storage_entry(binding: u32, read_only: bool) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: ShaderStages::COMPUTE,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Storage { read_only },
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    }
+}
+
+fn init_bind_group_layout(device: &Device) -> BindGroupLayout {
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: None,
+        entries: &[
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: ShaderStages::COMPUTE,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            },
+            storage_entry(1, true),
+            storage_entry(2, false),
+            storage_entry(3, true),
+            storage_entry(4, false),
+            storage_entry(5, false),
+            storage_entry(6, true),
+            storage_entry(7, false),
+            storage_entry(8, false),
+        ],
+    })
+}
+
+/// Packs cell states one byte per cell, in the layout the shaders expect.
+pub fn cell_states(universe: &Universe) -> Vec<u32> {
+    pack_words(universe, 0..universe.cells.len().div_ceil(4))
+}
+
+/// Packs just the `words` range of the state buffer.
+fn pack_words(universe: &Universe, words: Range<usize>) -> Vec<u32> {
+    let mut packed = vec![0; words.len()];
+    let cells = &universe.cells[words.start * 4..(words.end * 4).min(universe.cells.len())];
+    for (i, cell) in cells.iter().enumerate() {
+        packed[i / 4] |= (cell.state.index() as u32) << (i % 4 * 8);
+    }
+    packed
+}
+
+/// Change generations of the `cells` range, truncated to the `u32` the
+/// shaders keep.
+fn pack_changed(universe: &Universe, cells: Range<usize>) -> Vec<u32> {
+    universe.cells[cells]
+        .iter()
+        .map(|cell| {
+            cell.changed_at
+                .map_or(NEVER, |generation| generation as u32)
+        })
+        .collect()
+}
+
+/// Checks that the buffers for a `width` x `height` board under `rule` fit
+/// `limits`. The largest hold 4 bytes per cell: change generations, the heat
+/// map and, for Larger than Life rules, row sums with an extra word per row.
+pub fn check_limits(
+    limits: &wgpu::Limits,
+    width: u32,
+    height: u32,
+    rule: &Rule,
+) -> Result<(), String> {
+    let words_per_row = match rule.is_nearest() {
+        true => width as u64,
+        false => width as u64 + 1,
+    };
+    let largest = (words_per_row * height as u64).saturating_mul(std::mem::size_of::<u32>() as u64);
+    let limit = (limits.max_storage_buffer_binding_size as u64).min(limits.max_buffer_size);
+    if largest > limit {
+        return Err(format!(
+            "a {width}x{height} board needs {largest} byte GPU buffers, \
+             more than the limit of {limit}"
+        ));
+    }
+    let max_groups = limits
+        .max_compute_workgroups_per_dimension
+        .min(MAX_WORKGROUPS_PER_DIMENSION) as u64;
+    if (height as u64).div_ceil(WORKGROUP_SIZE as u64) > max_groups {
+        return Err(format!(
+            "a {width}x{height} board has too many rows for the GPU"
+        ));
+    }
+    Ok(())
+}
+
+impl LifeCompute {
+    /// Allocates the buffers for `universe` and uploads it, or fails when
+    /// they don't fit the device's limits.
+    pub fn new(device: &Device, universe: &Universe) -> Result<Self, String> {
+        check_limits(
+            &device.limits(),
+            universe.width(),
+            universe.height(),
+            &universe.rule,
+        )?;
+        let shader = device.create_shader_module(include_wgsl!("shaders/life.wgsl"));
+        let layout = init_bind_group_layout(device);
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: None,
+            layout: Some(&pipeline_layout),
+            module: &shader,
+            entry_point: "step",
+        });
+        let clock_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: None,
+            layout: Some(&pipeline_layout),
+            module: &shader,
+            entry_point: "advance_clock",
+        });
+        let row_sums_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: None,
+            layout: Some(&pipeline_layout),
+            module: &shader,
+            entry_point: "sum_rows",
+        });
+
+        let written_params = Params::new(universe);
+        let params = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: None,
+            contents: bytemuck::bytes_of(&written_params),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+        });
+
+        let contents = cell_states(universe);
+        let states = [0, 1].map(|_| {
+            device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: None,
+                contents: bytemuck::cast_slice(&contents),
+                usage: BufferUsages::STORAGE | BufferUsages::COPY_DST | BufferUsages::COPY_SRC,
+            })
+        });
+        let contents = pack_changed(universe, 0..universe.cells.len());
+        let changed = [0, 1].map(|_| {
+            device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: None,
+                contents: bytemuck::cast_slice(&contents),
+                usage: BufferUsages::STORAGE | BufferUsages::COPY_DST | BufferUsages::COPY_SRC,
+            })
+        });
+        let contents = universe.heat().unwrap_or(&[0.0]);
+        let heat = [0, 1].map(|_| {
+            device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: None,
+                contents: bytemuck::cast_slice(contents),
+                usage: BufferUsages::STORAGE | BufferUsages::COPY_DST | BufferUsages::COPY_SRC,
+            })
+        });
+        let clock = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: None,
+            contents: bytemuck::bytes_of(&(universe.generation() as u32)),
+            usage: BufferUsages::STORAGE | BufferUsages::COPY_DST,
+        });
+        let has_row_sums = !universe.rule.is_nearest();
+        let row_sums_words = match has_row_sums {
+            true => (universe.width() + 1) * universe.height(),
+            false => 1,
+        };
+        let row_sums = device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: (row_sums_words as usize * std::mem::size_of::<u32>()) as wgpu::BufferAddress,
+            usage: BufferUsages::STORAGE,
+            mapped_at_creation: false,
+        });
+
+        // bind_groups[i] reads states[i] and changed[i] and writes the others
+        let bind_groups = [0, 1].map(|i| {
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: None,
+                layout: &layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: params.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: states[i].as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: states[1 - i].as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 3,
+                        resource: changed[i].as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 4,
+                        resource: changed[1 - i].as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 5,
+                        resource: clock.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 6,
+                        resource: heat[i].as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 7,
+                        resource: heat[1 - i].as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 8,
+                        resource: row_sums.as_entire_binding(),
+                    },
+                ],
+            })
+        });
+
+        Ok(Self {
+            pipeline,
+            clock_pipeline,
+            row_sums_pipeline,
+            params,
+            written_params,
+            states,
+            changed,
+            clock,
+            heat,
+            has_heat: universe.heat().is_some(),
+            _row_sums: row_sums,
+            has_row_sums,
+            generation: universe.generation(),
+            bind_groups,
+            current: 0,
+            width: universe.width(),
+            height: universe.height(),
+        })
+    }
+
+    pub fn cell_count(&self) -> usize {
+        self.width as usize * self.height as usize
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn states(&self) -> &[Buffer; 2] {
+        &self.states
+    }
+
+    pub fn changed(&self) -> &[Buffer; 2] {
+        &self.changed
+    }
+
+    pub fn clock(&self) -> &Buffer {
+        &self.clock
+    }
+
+    pub fn heat(&self) -> &[Buffer; 2] {
+        &self.heat
+    }
+
+    /// Whether there is room for the row sums Larger than Life rules need.
+    pub fn has_row_sums(&self) -> bool {
+        self.has_row_sums
+    }
+
+    /// Cell states of the rule last written by `write_params`.
+    pub fn rule_states(&self) -> u32 {
+        self.written_params.states
+    }
+
+    /// Whether `heat()` holds a heat map rather than placeholders.
+    pub fn has_heat(&self) -> bool {
+        self.has_heat
+    }
+
+    /// Which of `states()` and `changed()` holds the latest generation.
+    pub fn current_index(&self) -> usize {
+        self.current
+    }
+
+    pub fn current(&self) -> &Buffer {
+        &self.states[self.current]
+    }
+
+    /// Copies `rows` of `universe` into the latest generation on the GPU.
+    pub fn upload_rows(&self, queue: &Queue, universe: &Universe, rows: Range<u32>) {
+        let first_cell = rows.start as usize * self.width as usize;
+        let end_cell = rows.end as usize * self.width as usize;
+        let words = first_cell / 4..end_cell.div_ceil(4);
+        let word_size = std::mem::size_of::<u32>();
+        queue.write_buffer(
+            self.current(),
+            (words.start * word_size) as wgpu::BufferAddress,
+            bytemuck::cast_slice(&pack_words(universe, words)),
+        );
+        queue.write_buffer(
+            &self.changed[self.current],
+            (first_cell * word_size) as wgpu::BufferAddress,
+            bytemuck::cast_slice(&pack_changed(universe, first_cell..end_cell)),
+        );
+        if let (true, Some(heat)) = (self.has_heat, universe.heat()) {
+            queue.write_buffer(
+                &self.heat[self.current],
+                (first_cell * word_size) as wgpu::BufferAddress,
+                bytemuck::cast_slice(&heat[first_cell..end_cell]),
+            );
+        }
+    }
+
+    /// Updates the rule, topology and generation, if they changed since the
+    /// last call.
+    pub fn write_params(&mut self, queue: &Queue, universe: &Universe) {
+        let params = Params::new(universe);
+        if params != self.written_params {
+            queue.write_buffer(&self.params, 0, bytemuck::bytes_of(&params));
+            self.written_params = params;
+        }
+        if universe.generation() != self.generation {
+            let clock = universe.generation() as u32;
+            queue.write_buffer(&self.clock, 0, bytemuck::bytes_of(&clock));
+            self.generation = universe.generation();
+        }
+    }
+
+    /// Records `generations` steps into `encoder`.
+    pub fn step(&mut self, encoder: &mut wgpu::CommandEncoder, generations: u32) {
+        let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor::default());
+        let words = (self.cell_count() as u32).div_ceil(4);
+        let groups = words.div_ceil(WORKGROUP_SIZE);
+        let groups_x = groups.clamp(1, MAX_WORKGROUPS_PER_DIMENSION);
+        for _ in 0..generations {
+            pass.set_bind_group(0, &self.bind_groups[self.current], &[]);
+            if self.has_row_sums {
+                pass.set_pipeline(&self.row_sums_pipeline);
+                pass.dispatch_workgroups(self.height.div_ceil(WORKGROUP_SIZE), 1, 1);
+            }
+            pass.set_pipeline(&self.pipeline);
+            pass.dispatch_workgroups(groups_x, groups.div_ceil(groups_x), 1);
+            pass.set_pipeline(&self.clock_pipeline);
+            pass.dispatch_workgroups(1, 1, 1);
+            self.current = 1 - self.current;
+        }
+        self.generation += generations as u64;
+    }
+
+    /// Reads the latest generation back into `universe`. Blocks until the
+    /// GPU is done with everything submitted so far.
+    pub fn download(&self, device: &Device, queue: &Queue, universe: &mut Universe) {
+        let states_size = self.current().size();
+        let changed = &self.changed[self.current];
+        let heat = &self.heat[self.current];
+        let heat_offset = states_size + changed.size();
+        let staging = device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: heat_offset + heat.size(),
+            usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+        encoder.copy_buffer_to_buffer(self.current(), 0, &staging, 0, states_size);
+        encoder.copy_buffer_to_buffer(changed, 0, &staging, states_size, changed.size());
+        encoder.copy_buffer_to_buffer(heat, 0, &staging, heat_offset, heat.size());
+        queue.submit(std::iter::once(encoder.finish()));
+
+        let slice = staging.slice(..);
+        slice.map_async(wgpu::MapMode::Read, |result| result.unwrap());
+        device.poll(wgpu::Maintain::Wait);
+
+        let data = slice.get_mapped_range();
+        let (states, rest) = data.split_at(states_size as usize);
+        let (changed, heat) = rest.split_at(changed.size() as usize);
+        let changed: &[u32] = bytemuck::cast_slice(changed);
+        // The GPU only keeps the low bits of generations, which is enough to
+        // recover how long ago each cell changed.
+        let generation = universe.generation();
+        let clock = generation as u32;
+        for ((cell, &state), &changed_at) in universe.cells.iter_mut().zip(states).zip(changed) {
+            cell.state = CellState::from_index(state);
+            cell.changed_at = (changed_at != NEVER)
+                .then(|| generation.saturating_sub(clock.wrapping_sub(changed_at) as u64));
+        }
+        if let (true, Some(values)) = (self.has_heat, universe.heat_mut()) {
+            values.copy_from_slice(bytemuck::cast_slice(heat));
+        }
+        drop(data);
+        staging.unmap();
+        universe.mark_clean();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `width` by `height` board under `rule` with `cells` alive.
+    fn board(width: u32, height: u32, rule: &str, cells: &[(u32, u32)]) -> Universe {
+        let mut universe = Universe::new(width, height, rule.parse().unwrap());
+        for &(x, y) in cells {
+            universe.set(x, y, CellState::Alive);
+        }
+        universe
+    }
+
+    /// Board with roughly `percent` of its cells alive, the same every run.
+    fn soup(width: u32, height: u32, rule: &str, percent: u32, seed: u64) -> Universe {
+        let mut rng = fastrand::Rng::with_seed(seed);
+        let cells: Vec<_> = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .filter(|_| rng.u32(0..100) < percent)
+            .collect();
+        board(width, height, rule, &cells)
+    }
+
+    /// Steps `universe` on the GPU one generation at a time, checking every
+    /// one against `Universe::tick`.
+    fn assert_matches_cpu(
+        device: &Device,
+        queue: &Queue,
+        mut universe: Universe,
+        generations: u64,
+    ) {
+        let mut life = LifeCompute::new(device, &universe).unwrap();
+        let mut reference = universe.clone();
+        for _ in 0..generations {
+            let mut encoder = device.create_command_encoder(&Default::default());
+            life.step(&mut encoder, 1);
+            queue.submit(std::iter::once(encoder.finish()));
+            universe.set_generation(universe.generation() + 1);
+            life.download(device, queue, &mut universe);
+            reference.tick();
+
+            let states = |u: &Universe| u.cells.iter().map(|c| c.state).collect::<Vec<_>>();
+            let ages = |u: &Universe| u.cells.iter().map(|c| c.changed_at).collect::<Vec<_>>();
+            let rule = reference.rule;
+            let generation = reference.generation();
+            assert!(
+                states(&universe) == states(&reference),
+                "{rule} differs at {generation}"
+            );
+            assert!(
+                ages(&universe) == ages(&reference),
+                "{rule} ages differ at {generation}"
+            );
+        }
+    }
+
+    #[test]
+    fn gpu_steps_match_the_cpu() {
+        let Ok((device, queue)) = pollster::block_on(crate::gpu::headless_device(true)) else {
+            eprintln!("skipped: no fallback GPU adapter");
+            return;
+        };
+
+        let glider = [(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)];
+        assert_matches_cpu(&device, &queue, board(16, 16, "B3/S23", &glider), 40);
+        let mut torus = board(12, 10, "B3/S23", &glider);
+        torus.topology = Topology::Torus;
+        assert_matches_cpu(&device, &queue, torus, 60);
+
+        assert_matches_cpu(&device, &queue, soup(32, 24, "B2/S/C3", 30, 1), 30);
+        assert_matches_cpu(
+            &device,
+            &queue,
+            soup(40, 40, "R5,C0,M1,S34..58,B34..45,NM", 45, 2),
+            20,
+        );
+        assert_matches_cpu(&device, &queue, soup(32, 32, "B3/S23-a4i", 35, 3), 30);
+    }
+
+    #[test]
+    fn boards_past_the_buffer_limits_are_rejected() {
+        let limits = wgpu::Limits::default();
+        let life: Rule = "B3/S23".parse().unwrap();
+        let bosco: Rule = "R5,C0,M1,S34..58,B34..45,NM".parse().unwrap();
+        // 4 bytes a cell, plus a word a row for row sums.
+        let side = 1 << 12;
+        let rows = limits.max_storage_buffer_binding_size / 4 / side;
+        assert!(check_limits(&limits, side, rows, &life).is_ok());
+        assert!(check_limits(&limits, side, rows, &bosco).is_err());
+        assert!(check_limits(&limits, side, rows + 1, &life).is_err());
+        assert!(check_limits(&limits, u32::MAX, u32::MAX, &bosco).is_err());
+    }
+}
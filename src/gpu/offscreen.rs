@@ -0,0 +1,191 @@
+use std::path::Path;
+
+use egui_winit::winit::dpi::PhysicalSize;
+use wgpu::{Device, Queue};
+
+use super::{
+    board::{init_board, BoardBuffers},
+    compute::LifeCompute,
+    pipeline::{init_pipeline, Pipeline},
+    uniform::init_uniforms,
+    vertex::{init_buffers, VertexBuffer},
+};
+use crate::{camera::Camera, config::DisplayConfig};
+
+/// Offscreen targets are always RGBA, so pixels go straight into a PNG.
+const FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+
+/// RGBA pixels read back from the GPU, rows tightly packed.
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+impl Image {
+    pub fn save_png(&self, path: &Path) -> Result<(), png::EncodingError> {
+        let file = std::io::BufWriter::new(std::fs::File::create(path)?);
+        let mut encoder = png::Encoder::new(file, self.width, self.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.write_header()?.write_image_data(&self.pixels)
+    }
+}
+
+/// Draws the latest generation in `life`, seen through `camera`, into a new
+/// `size` image with the same pipeline the window uses. Works on any adapter,
+/// including wgpu's software fallback, since nothing here needs a surface.
+pub fn render(
+    device: &Device,
+    queue: &Queue,
+    life: &LifeCompute,
+    camera: &Camera,
+    size: (u32, u32),
+    display: &DisplayConfig,
+) -> Result<Image, String> {
+    Ok(Offscreen::new(device, life, camera, size, display)?.render(device, queue, life))
+}
+
+/// A fixed-size render target for drawing many frames of the same board,
+/// keeping the pipeline and buffers around between frames.
+pub struct Offscreen {
+    pipeline: Pipeline,
+    board: BoardBuffers,
+    square: VertexBuffer,
+    texture: wgpu::Texture,
+    view: wgpu::TextureView,
+    readback: wgpu::Buffer,
+    size: (u32, u32),
+    padded_row_bytes: u32,
+}
+
+impl Offscreen {
+    pub fn new(
+        device: &Device,
+        life: &LifeCompute,
+        camera: &Camera,
+        size: (u32, u32),
+        display: &DisplayConfig,
+    ) -> Result<Self, String> {
+        let (width, height) = size;
+        let max = device.limits().max_texture_dimension_2d;
+        if width == 0 || height == 0 || width > max || height > max {
+            return Err(format!(
+                "can't render a {width}x{height} image, sizes must be between 1 and {max}"
+            ));
+        }
+
+        let uniforms = init_uniforms(device, PhysicalSize::new(width, height), camera);
+        let pipeline = init_pipeline(device, FORMAT, &uniforms);
+        let board = init_board(
+            device,
+            &pipeline.bindgroups.board_layout,
+            life,
+            FORMAT,
+            display,
+        );
+        let square = init_buffers(device);
+
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: None,
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        // Texture copies need rows aligned to 256 bytes; the padding is
+        // dropped again when reading back.
+        let padded_row_bytes = (width * 4).next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+        let readback = device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: (padded_row_bytes * height) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        Ok(Self {
+            pipeline,
+            board,
+            square,
+            texture,
+            view,
+            readback,
+            size,
+            padded_row_bytes,
+        })
+    }
+
+    /// Draws the latest generation in `life` and reads the pixels back.
+    /// `life` must be the one this target was created with.
+    pub fn render(&self, device: &Device, queue: &Queue, life: &LifeCompute) -> Image {
+        let (width, height) = self.size;
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+        {
+            let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: None,
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &self.view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(self.board.clear_color()),
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            });
+            self.board
+                .draw(&mut pass, &self.pipeline, &self.square, life);
+        }
+        encoder.copy_texture_to_buffer(
+            wgpu::ImageCopyTexture {
+                texture: &self.texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            wgpu::ImageCopyBuffer {
+                buffer: &self.readback,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: std::num::NonZeroU32::new(self.padded_row_bytes),
+                    rows_per_image: None,
+                },
+            },
+            wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+        );
+        queue.submit(std::iter::once(encoder.finish()));
+
+        let slice = self.readback.slice(..);
+        slice.map_async(wgpu::MapMode::Read, |result| result.unwrap());
+        device.poll(wgpu::Maintain::Wait);
+
+        let data = slice.get_mapped_range();
+        let row_bytes = (width * 4) as usize;
+        let pixels = data
+            .chunks(self.padded_row_bytes as usize)
+            .flat_map(|row| &row[..row_bytes])
+            .copied()
+            .collect();
+        drop(data);
+        self.readback.unmap();
+
+        Image {
+            width,
+            height,
+            pixels,
+        }
+    }
+}
@@ -1,19 +1,32 @@
 use wgpu::{
     include_wgsl, BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout,
     BindGroupLayoutDescriptor, Device, FragmentState, MultisampleState, PipelineLayoutDescriptor,
-    PrimitiveState, PrimitiveTopology, RenderPipeline, ShaderStages, SurfaceConfiguration,
-    VertexState,
+    PrimitiveState, PrimitiveTopology, RenderPipeline, ShaderStages, TextureFormat, VertexState,
 };
 
-use super::{instance::InstanceBuffers, uniform::UniformBuffers, vertex::Vertex2d};
+use super::{uniform::UniformBuffers, vertex::Vertex2d};
 
 pub struct BindGroups {
-    pub projection_mat: BindGroup,
-    pub projection_mat_layout: BindGroupLayout,
+    pub camera: BindGroup,
+    pub camera_layout: BindGroupLayout,
+    pub board_layout: BindGroupLayout,
+}
+
+fn fragment_storage_entry(binding: u32) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        count: None,
+        visibility: ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Storage { read_only: true },
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+    }
 }
 
 pub fn init_bind_groups(device: &Device, buffers: &UniformBuffers) -> BindGroups {
-    let projection_mat_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+    let camera_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
         label: None,
         entries: &[wgpu::BindGroupLayoutEntry {
             binding: 0,
@@ -26,17 +39,37 @@ pub fn init_bind_groups(device: &Device, buffers: &UniformBuffers) -> BindGroups
             },
         }],
     });
-    let projection_mat = device.create_bind_group(&BindGroupDescriptor {
+    let camera = device.create_bind_group(&BindGroupDescriptor {
         label: None,
-        layout: &projection_mat_layout,
+        layout: &camera_layout,
         entries: &[BindGroupEntry {
             binding: 0,
-            resource: buffers.projection_mat.as_entire_binding(),
+            resource: buffers.camera.as_entire_binding(),
         }],
     });
+    let board_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+        label: None,
+        entries: &[
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                count: None,
+                visibility: ShaderStages::VERTEX_FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+            },
+            fragment_storage_entry(1),
+            fragment_storage_entry(2),
+            fragment_storage_entry(3),
+            fragment_storage_entry(4),
+        ],
+    });
     BindGroups {
-        projection_mat,
-        projection_mat_layout,
+        camera,
+        camera_layout,
+        board_layout,
     }
 }
 
@@ -45,17 +78,13 @@ pub struct Pipeline {
     pub bindgroups: BindGroups,
 }
 
-pub fn init_pipeline(
-    device: &Device,
-    config: &SurfaceConfiguration,
-    buffers: &UniformBuffers,
-) -> Pipeline {
-    let square_shader = device.create_shader_module(include_wgsl!("shaders/square.wgsl"));
+pub fn init_pipeline(device: &Device, format: TextureFormat, buffers: &UniformBuffers) -> Pipeline {
+    let board_shader = device.create_shader_module(include_wgsl!("shaders/board.wgsl"));
     let bindgroups = init_bind_groups(device, buffers);
 
     let layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
         label: None,
-        bind_group_layouts: &[&bindgroups.projection_mat_layout],
+        bind_group_layouts: &[&bindgroups.camera_layout, &bindgroups.board_layout],
         push_constant_ranges: &[],
     });
 
@@ -71,16 +100,16 @@ pub fn init_pipeline(
 
         layout: Some(&layout),
         vertex: VertexState {
-            module: &square_shader,
-            buffers: &[Vertex2d::desc(), InstanceBuffers::cell_desc()],
+            module: &board_shader,
+            buffers: &[Vertex2d::desc()],
             entry_point: "vs_main",
         },
         fragment: Some(FragmentState {
             entry_point: "fs_main",
-            module: &square_shader,
+            module: &board_shader,
             targets: &[Some(wgpu::ColorTargetState {
                 blend: Some(wgpu::BlendState::REPLACE),
-                format: config.format,
+                format,
                 write_mask: wgpu::ColorWrites::ALL,
             })],
         }),
@@ -0,0 +1,128 @@
+struct Camera {
+    proj: mat4x4<f32>,
+    view: mat4x4<f32>,
+};
+
+@group(0) @binding(0)
+var<uniform> camera: Camera;
+
+struct Board {
+    width: u32,
+    height: u32,
+    gap: f32,
+    // 0: by state, 1: live cells by age
+    coloring: u32,
+    age_span: u32,
+    // Generations dead cells take to fade out, 0 for no trails.
+    trail_length: u32,
+    heat_map: u32,
+    grid: u32,
+    // Cell states of the rule, above 2 for Generations rules.
+    states: u32,
+    alive: vec4<f32>,
+    young: vec4<f32>,
+    dying: vec4<f32>,
+    dead: vec4<f32>,
+    heat: vec4<f32>,
+    grid_color: vec4<f32>,
+    background: vec4<f32>,
+};
+
+@group(1) @binding(0)
+var<uniform> board: Board;
+
+// One byte per cell, four cells packed in each word, lowest byte first.
+@group(1) @binding(1)
+var<storage, read> states: array<u32>;
+
+// Generation each cell entered its current state in, and the current one.
+@group(1) @binding(2)
+var<storage, read> changed: array<u32>;
+
+@group(1) @binding(3)
+var<storage, read> clock: u32;
+
+// How often each cell was alive lately, from 0 to 1, when heat_map is set.
+@group(1) @binding(4)
+var<storage, read> heat: array<f32>;
+
+// Change generation of cells that have never been alive.
+const NEVER: u32 = 0xffffffffu;
+
+struct VertexOutput {
+    @builtin(position) pos: vec4<f32>,
+    @location(0) world: vec2<f32>,
+};
+
+// The unit quad is stretched over the whole board, and each fragment works
+// out which cell it belongs to.
+@vertex
+fn vs_main(@location(0) pos: vec2<f32>) -> VertexOutput {
+    let world = (pos + 0.5) * vec2<f32>(f32(board.width), f32(board.height));
+
+    var out: VertexOutput;
+    out.pos = camera.proj * camera.view * vec4<f32>(world, 1.0, 1.0);
+    out.world = world;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    // Size of a pixel in cells. Gaps thinner than a pixel would only alias,
+    // so they are dropped when zoomed far out.
+    let pixel = fwidth(in.world.x);
+    let local = fract(in.world);
+    let half_gap = board.gap / 2.0;
+    let in_gap = board.gap >= pixel && (any(local < vec2<f32>(half_gap)) || any(local > vec2<f32>(1.0 - half_gap)));
+
+    var color = board.background;
+    if (!in_gap) {
+        let cell = min(vec2<u32>(in.world), vec2<u32>(board.width - 1u, board.height - 1u));
+        let idx = cell.x + cell.y * board.width;
+        color = cell_color(idx);
+        if (board.heat_map == 1u) {
+            // Leave some of the cell showing through even on the hottest spots.
+            color = mix(color, board.heat, heat[idx] * 0.85);
+        }
+    }
+    if (board.grid == 1u) {
+        // Lines between cells fade in from 4 to 8 pixels per cell, and major
+        // lines every 10 cells the same way, so the grid never turns to mush.
+        let minor = line_coverage(in.world, pixel) * smoothstep(4.0, 8.0, 1.0 / pixel);
+        let major = line_coverage(in.world / 10.0, pixel / 10.0) * smoothstep(4.0, 8.0, 10.0 / pixel);
+        color = mix(color, board.grid_color, max(minor * 0.6, major));
+    }
+    return color;
+}
+
+// 1 on a one pixel wide line along integer coordinates, 0 a pixel away.
+// `pixel` is the size of a pixel in the same units as `pos`.
+fn line_coverage(pos: vec2<f32>, pixel: f32) -> f32 {
+    let distance = abs(fract(pos + 0.5) - 0.5) / pixel;
+    return 1.0 - min(min(distance.x, distance.y), 1.0);
+}
+
+fn cell_color(idx: u32) -> vec4<f32> {
+    let state = (states[idx / 4u] >> ((idx % 4u) * 8u)) & 0xffu;
+    let age = clock - changed[idx];
+    if (state >= 2u) {
+        // Dying states fade from the dying color towards dead.
+        let t = f32(state - 2u) / f32(max(board.states, 3u) - 2u);
+        return mix(board.dying, board.dead, t);
+    }
+    if (state != 1u) {
+        if (changed[idx] != NEVER && age < board.trail_length) {
+            // Start half way, so trails never pass for live cells.
+            let t = f32(age + 1u) / f32(board.trail_length + 1u);
+            return mix(board.alive, board.dead, 0.5 + 0.5 * t);
+        }
+        return board.dead;
+    }
+    if (board.coloring == 1u) {
+        // Logarithmic, so blinkers and gliders are told apart as easily as
+        // debris and still lifes.
+        let t = min(log2(f32(age) + 1.0) / log2(f32(board.age_span) + 1.0), 1.0);
+        return mix(board.young, board.alive, t);
+    }
+    return board.alive;
+}
@@ -0,0 +1,256 @@
+struct Params {
+    width: u32,
+    height: u32,
+    torus: u32,
+    // 0 when the heat map is off.
+    heat_window: u32,
+    // Above 2, cells that don't survive go through states 2, 3, ... before
+    // dying (Generations rules).
+    states: u32,
+    // How far neighbors are counted, 1 for the 8 nearest ones.
+    range: u32,
+    // 0: Moore, 1: von Neumann, 2: circular
+    neighborhood: u32,
+    // 1 when a cell counts as its own neighbor.
+    middle: u32,
+    // Bit n set => a cell with n live neighbors is born / survives.
+    birth: array<vec4<u32>, 4>,
+    survival: array<vec4<u32>, 4>,
+    // Bit n set => a cell whose live nearest neighbors form arrangement n
+    // (see `rule::NEIGHBORS`) is born / survives. Used instead of the counts
+    // for range 1 Moore neighborhoods without the middle cell.
+    birth_arrangements: array<vec4<u32>, 2>,
+    survival_arrangements: array<vec4<u32>, 2>,
+};
+
+@group(0) @binding(0)
+var<uniform> params: Params;
+
+// One byte per cell, four cells packed in each word, lowest byte first.
+@group(0) @binding(1)
+var<storage, read> current: array<u32>;
+
+@group(0) @binding(2)
+var<storage, read_write> next: array<u32>;
+
+// Generation each cell entered its current state in, one word per cell, or
+// NEVER for cells that haven't been alive since the board was cleared.
+@group(0) @binding(3)
+var<storage, read> current_changed: array<u32>;
+
+@group(0) @binding(4)
+var<storage, read_write> next_changed: array<u32>;
+
+// Generation of `current`. Advanced by `advance_clock` after every step.
+@group(0) @binding(5)
+var<storage, read_write> clock: u32;
+
+// Moving average of how often each cell was alive, when heat_window > 0.
+@group(0) @binding(6)
+var<storage, read> current_heat: array<f32>;
+
+@group(0) @binding(7)
+var<storage, read_write> next_heat: array<f32>;
+
+// Live cells in each row up to each column, width + 1 words per row starting
+// with a zero, filled by `sum_rows` for Larger than Life rules.
+@group(0) @binding(8)
+var<storage, read_write> row_sums: array<u32>;
+
+// Same as `universe::HEAT_EPSILON`.
+const HEAT_EPSILON: f32 = 0.001;
+
+fn next_heat_value(idx: u32, alive: u32) -> f32 {
+    let heat = current_heat[idx];
+    let goal = f32(alive);
+    let next = heat + (goal - heat) * (1.0 / f32(params.heat_window));
+    return select(next, goal, abs(goal - next) < HEAT_EPSILON);
+}
+
+fn state_at(idx: u32) -> u32 {
+    return (current[idx / 4u] >> ((idx % 4u) * 8u)) & 0xffu;
+}
+
+fn alive_at(x: i32, y: i32) -> u32 {
+    let w = i32(params.width);
+    let h = i32(params.height);
+    var nx = x;
+    var ny = y;
+    if (params.torus == 1u) {
+        nx = (nx + w) % w;
+        ny = (ny + h) % h;
+    } else if (nx < 0 || ny < 0 || nx >= w || ny >= h) {
+        return 0u;
+    }
+    return u32(state_at(u32(nx) + u32(ny) * params.width) == 1u);
+}
+
+// Whether `n` is in the birth or survival counts.
+fn counts_bit(survival: bool, n: u32) -> u32 {
+    if (n >= 512u) {
+        return 0u;
+    }
+    var word = params.birth[n / 128u];
+    if (survival) {
+        word = params.survival[n / 128u];
+    }
+    return (word[(n / 32u) % 4u] >> (n % 32u)) & 1u;
+}
+
+// Whether arrangement `n` is in the birth or survival arrangements.
+fn arrangements_bit(survival: bool, n: u32) -> u32 {
+    var word = params.birth_arrangements[n / 128u];
+    if (survival) {
+        word = params.survival_arrangements[n / 128u];
+    }
+    return (word[(n / 32u) % 4u] >> (n % 32u)) & 1u;
+}
+
+// Same as `Neighborhood::half_width`.
+fn half_width(dy: u32) -> u32 {
+    let r = params.range;
+    if (params.neighborhood == 1u) {
+        return r - dy;
+    }
+    if (params.neighborhood == 2u) {
+        let limit = r * r + r - dy * dy;
+        var width = r - dy;
+        while ((width + 1u) * (width + 1u) <= limit) {
+            width++;
+        }
+        return width;
+    }
+    return r;
+}
+
+// `i.rem_euclid(n)` for `i >= -range`, keeping `%` away from negative
+// operands.
+fn wrap(i: i32, n: i32) -> i32 {
+    let laps = i32(params.range) / n + 1;
+    return i32(u32(i + laps * n) % u32(n));
+}
+
+// Same as `universe::row_count`.
+fn row_count(row: u32, a: i32, b: i32) -> u32 {
+    let w = i32(params.width);
+    let base = row * (params.width + 1u);
+    if (params.torus == 0u) {
+        let lo = max(a, 0);
+        let hi = min(b, w - 1);
+        return row_sums[base + u32(hi) + 1u] - row_sums[base + u32(lo)];
+    }
+    let len = b - a + 1;
+    let laps = len / w;
+    let rest = len % w;
+    let start = wrap(a, w);
+    let end = start + rest;
+    let total = row_sums[base + params.width];
+    var partial = 0u;
+    if (end <= w) {
+        partial = row_sums[base + u32(end)] - row_sums[base + u32(start)];
+    } else {
+        partial = total - row_sums[base + u32(start)] + row_sums[base + u32(end - w)];
+    }
+    return u32(laps) * total + partial;
+}
+
+// Live cells in a Larger than Life neighborhood, the middle one included.
+fn range_neighbors(x: i32, y: i32) -> u32 {
+    let r = i32(params.range);
+    let h = i32(params.height);
+    var count = 0u;
+    for (var dy = -r; dy <= r; dy++) {
+        var row = y + dy;
+        if (params.torus == 1u) {
+            row = wrap(row, h);
+        } else if (row < 0 || row >= h) {
+            continue;
+        }
+        let half = i32(half_width(u32(abs(dy))));
+        count += row_count(u32(row), x - half, x + half);
+    }
+    return count;
+}
+
+fn next_state(idx: u32) -> u32 {
+    let state = state_at(idx);
+    if (state >= 2u) {
+        return select(0u, state + 1u, state + 1u < params.states);
+    }
+
+    let x = i32(idx % params.width);
+    let y = i32(idx / params.width);
+    var next = 0u;
+    if (params.range == 1u && params.neighborhood == 0u && params.middle == 0u) {
+        // Neighbors in reading order, the first one in bit 7.
+        var arrangement = 0u;
+        for (var dy = -1; dy <= 1; dy++) {
+            for (var dx = -1; dx <= 1; dx++) {
+                if (dx != 0 || dy != 0) {
+                    arrangement = (arrangement << 1u) | alive_at(x + dx, y + dy);
+                }
+            }
+        }
+        next = arrangements_bit(state == 1u, arrangement);
+    } else {
+        var neighbors = range_neighbors(x, y);
+        if (params.middle == 0u && state == 1u) {
+            neighbors -= 1u;
+        }
+        next = counts_bit(state == 1u, neighbors);
+    }
+
+    if (state == 1u && next == 0u && params.states > 2u) {
+        return 2u;
+    }
+    return next;
+}
+
+// Each invocation writes one whole word, so no two invocations ever touch the
+// same memory. Large boards spill over into the y dimension of the dispatch.
+@compute @workgroup_size(64)
+fn step(
+    @builtin(global_invocation_id) id: vec3<u32>,
+    @builtin(num_workgroups) groups: vec3<u32>,
+) {
+    let word = id.x + id.y * groups.x * 64u;
+    let cells = params.width * params.height;
+    if (word * 4u >= cells) {
+        return;
+    }
+
+    var word_states = 0u;
+    for (var i = 0u; i < 4u; i++) {
+        let idx = word * 4u + i;
+        if (idx < cells) {
+            let state = next_state(idx);
+            word_states |= state << (i * 8u);
+            next_changed[idx] = select(current_changed[idx], clock + 1u, state != state_at(idx));
+            if (params.heat_window > 0u) {
+                next_heat[idx] = next_heat_value(idx, u32(state == 1u));
+            }
+        }
+    }
+    next[word] = word_states;
+}
+
+// One invocation per row.
+@compute @workgroup_size(64)
+fn sum_rows(@builtin(global_invocation_id) id: vec3<u32>) {
+    let y = id.x;
+    if (y >= params.height) {
+        return;
+    }
+    let base = y * (params.width + 1u);
+    var sum = 0u;
+    row_sums[base] = 0u;
+    for (var x = 0u; x < params.width; x++) {
+        sum += u32(state_at(x + y * params.width) == 1u);
+        row_sums[base + x + 1u] = sum;
+    }
+}
+
+@compute @workgroup_size(1)
+fn advance_clock() {
+    clock += 1u;
+}
@@ -5,21 +5,41 @@ use wgpu::{
 };
 use winit::dpi::PhysicalSize;
 
+use crate::camera::Camera;
 use crate::math::ortho_projection;
 
+#[repr(C)]
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct CameraUniform {
+    pub projection: [f32; 16],
+    pub view: [f32; 16],
+}
+
+impl CameraUniform {
+    pub fn new(dimensions: PhysicalSize<u32>, camera: &Camera) -> Self {
+        Self {
+            projection: ortho_projection((dimensions.width, dimensions.height)),
+            view: camera.view_matrix(),
+        }
+    }
+}
+
 pub struct UniformBuffers {
-    pub projection_mat: Buffer,
+    pub camera: Buffer,
 }
 
-pub fn init_uniforms(device: &Device, dimensions: PhysicalSize<u32>) -> UniformBuffers {
-    let dimensions = (dimensions.width, dimensions.height);
-    let projection_mat = ortho_projection(dimensions);
+pub fn init_uniforms(
+    device: &Device,
+    dimensions: PhysicalSize<u32>,
+    camera: &Camera,
+) -> UniformBuffers {
+    let uniform = CameraUniform::new(dimensions, camera);
 
-    let projection_mat = device.create_buffer_init(&BufferInitDescriptor {
+    let camera = device.create_buffer_init(&BufferInitDescriptor {
         label: None,
-        contents: bytemuck::cast_slice(&projection_mat),
-        usage: BufferUsages::UNIFORM,
+        contents: bytemuck::bytes_of(&uniform),
+        usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
     });
 
-    UniformBuffers { projection_mat }
+    UniformBuffers { camera }
 }
@@ -47,12 +47,12 @@ pub struct VertexBuffer {
 pub fn init_buffers(device: &Device) -> VertexBuffer {
     let vertex = device.create_buffer_init(&BufferInitDescriptor {
         label: None,
-        contents: bytemuck::cast_slice(&VERTICES[..]),
+        contents: bytemuck::cast_slice(VERTICES),
         usage: BufferUsages::VERTEX,
     });
     let index = device.create_buffer_init(&BufferInitDescriptor {
         label: None,
-        contents: bytemuck::cast_slice(&INDICES[..]),
+        contents: bytemuck::cast_slice(INDICES),
         usage: BufferUsages::INDEX,
     });
     VertexBuffer { vertex, index }
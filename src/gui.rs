@@ -1,16 +1,425 @@
 use egui_winit::winit;
 
+use crate::animation::{FrameRange, Style, DEFAULT_CELL_SIZE, DEFAULT_GAP};
+use crate::camera::Camera;
+use crate::config::{Config, ConfirmConfig, DisplayConfig};
+use crate::minimap::{self, Minimap};
+use crate::rule::Rule;
+use crate::svg::SvgStyle;
+use crate::theme::{Coloring, Rgb, Theme, ThemePreset};
+use crate::tools::{Run, Tool};
+
+/// Something the user asked for in the GUI that has to be carried out by the
+/// application state after the frame is built.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    ResetSettings,
+    ResetToGenerationZero,
+    SaveSession,
+    LoadSession,
+    /// Switch to the rule typed in `rule_text`.
+    ApplyRule,
+    FitPattern,
+    ToggleFullscreen,
+    Screenshot,
+    ExportAnimation,
+    ExportSvg,
+    Clear,
+    /// Fill the selection, or the whole board, with random cells.
+    RandomFill,
+    /// The user agreed to go ahead with a destructive action.
+    Confirmed(Confirm),
+}
+
+/// A destructive action waiting for the user to confirm it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Confirm {
+    Clear,
+    LoadSession,
+    Quit,
+    RandomFill,
+}
+
+impl Confirm {
+    fn message(self) -> &'static str {
+        match self {
+            Confirm::Clear => "Kill every cell on the board?",
+            Confirm::LoadSession => "Load the session and lose the unsaved changes?",
+            Confirm::Quit => "Quit and lose the unsaved changes?",
+            Confirm::RandomFill => "Replace the cells with random ones?",
+        }
+    }
+
+    fn button(self) -> &'static str {
+        match self {
+            Confirm::Clear => "Clear",
+            Confirm::LoadSession => "Load",
+            Confirm::Quit => "Quit",
+            Confirm::RandomFill => "Fill",
+        }
+    }
+}
+
+/// Part of the board an export covers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExportRegion {
+    LiveCells,
+    View,
+    Board,
+}
+
 pub struct State {
     pub running: bool,
-    pub cell_scale_factor: f32,
-    pub cell_offset: f32,
-    pub clear_color_r: f64,
-    pub clear_color_g: f64,
-    pub clear_color_b: f64,
+    pub requested: Option<Action>,
+    pub generation: u64,
+    pub session_path: String,
+    pub status: String,
+    pub export_path: String,
+    /// Screenshot size as a multiple of the window size.
+    pub export_scale: u32,
+    pub export_status: String,
+    pub animation_path: String,
+    pub animation_range: FrameRange,
+    /// Animation cell size and gap in pixels; colors come from the theme.
+    pub animation_cell_size: u32,
+    pub animation_gap: u32,
+    /// Milliseconds between animation frames.
+    pub animation_delay: u32,
+    /// Crop animations to the visible area instead of fitting the live cells.
+    pub animation_crop_to_view: bool,
+    pub svg_path: String,
+    pub svg_merge_runs: bool,
+    pub svg_grid: bool,
+    pub svg_region: ExportRegion,
+    pub ticks_per_sec: u32,
+    pub edit_while_running: bool,
+    /// The rule field, applied with Enter or the "Apply" button.
+    pub rule_text: String,
+    /// Why `rule_text` couldn't be applied.
+    pub rule_error: Option<String>,
+    /// Percentage of cells a random fill brings to life.
+    pub fill_density: u32,
+    pub cell_gap: f32,
+    pub theme: Theme,
+    pub coloring: Coloring,
+    pub age_span: u32,
+    pub trail_length: u32,
+    pub heat_map: bool,
+    pub heat_window: u32,
+    pub grid: bool,
+    pub rulers: bool,
+    /// The board's camera this frame, for drawing rulers and the selection.
+    /// The minimap moves it, and the application picks the change up.
+    pub camera: Camera,
+    pub minimap_open: bool,
+    pub minimap: Option<Minimap>,
+    /// `minimap` was replaced since the last frame.
+    pub minimap_changed: bool,
+    pub cursor_cell: Option<(u32, u32)>,
+    pub shortcuts_open: bool,
+    /// Shown as a modal dialog until the user answers it.
+    pub confirm: Option<Confirm>,
+    pub confirm_clear: bool,
+    pub confirm_load: bool,
+    pub confirm_quit: bool,
+    pub confirm_random_fill: bool,
+    /// Action descriptions with the keys bound to them.
+    pub shortcuts: Vec<(&'static str, String)>,
+    /// Selected cells as `(x, y, width, height)`.
+    pub selection: Option<(u32, u32, u32, u32)>,
+    pub tool: Tool,
+    /// Rectangles and ellipses are drawn filled rather than as outlines.
+    pub fill_shapes: bool,
+    /// Cells the current stroke will change, as `(x, y, length)` runs.
+    pub preview: Vec<Run>,
+    /// The preview kills cells rather than bringing them to life.
+    pub preview_erases: bool,
+}
+
+impl State {
+    pub fn new(config: &Config) -> Self {
+        let mut state = Self {
+            running: false,
+            requested: None,
+            generation: 0,
+            session_path: "session.toml".to_owned(),
+            status: String::new(),
+            export_path: "screenshot.png".to_owned(),
+            export_scale: 1,
+            export_status: String::new(),
+            animation_path: "animation.gif".to_owned(),
+            animation_range: FrameRange {
+                from: 0,
+                to: 100,
+                step: 1,
+            },
+            animation_cell_size: DEFAULT_CELL_SIZE,
+            animation_gap: DEFAULT_GAP,
+            animation_delay: 100,
+            animation_crop_to_view: false,
+            svg_path: "board.svg".to_owned(),
+            svg_merge_runs: true,
+            svg_grid: false,
+            svg_region: ExportRegion::LiveCells,
+            ticks_per_sec: 0,
+            edit_while_running: true,
+            rule_text: String::new(),
+            rule_error: None,
+            fill_density: 25,
+            cell_gap: 0.0,
+            theme: Theme::default(),
+            coloring: Coloring::State,
+            age_span: 0,
+            trail_length: 0,
+            heat_map: false,
+            heat_window: 0,
+            grid: false,
+            rulers: false,
+            camera: Camera::default(),
+            minimap_open: false,
+            shortcuts_open: false,
+            confirm: None,
+            confirm_clear: true,
+            confirm_load: true,
+            confirm_quit: true,
+            confirm_random_fill: true,
+            shortcuts: Vec::new(),
+            minimap: None,
+            minimap_changed: false,
+            cursor_cell: None,
+            selection: None,
+            tool: Tool::Freehand,
+            fill_shapes: false,
+            preview: Vec::new(),
+            preview_erases: false,
+        };
+        state.apply_config(config);
+        state
+    }
+
+    pub fn apply_config(&mut self, config: &Config) {
+        self.apply_display(&config.display);
+        self.ticks_per_sec = config.simulation.speed.max(1);
+        self.edit_while_running = config.simulation.edit_while_running;
+        self.confirm_clear = config.confirm.clear;
+        self.confirm_load = config.confirm.load;
+        self.confirm_quit = config.confirm.quit;
+        self.confirm_random_fill = config.confirm.random_fill;
+    }
+
+    /// Fills the rule field with the rule now in use.
+    pub fn show_rule(&mut self, rule: Rule) {
+        self.rule_text = rule.to_string();
+        self.rule_error = None;
+    }
+
+    pub fn apply_display(&mut self, display: &DisplayConfig) {
+        self.theme = display.theme;
+        self.cell_gap = display.cell_gap;
+        self.coloring = display.coloring;
+        self.age_span = display.age_span;
+        self.trail_length = display.trail_length;
+        self.heat_map = display.heat_map;
+        self.heat_window = display.heat_window;
+        self.grid = display.grid;
+        self.rulers = display.rulers;
+    }
+
+    pub fn display(&self) -> DisplayConfig {
+        DisplayConfig {
+            theme: self.theme,
+            cell_gap: self.cell_gap,
+            coloring: self.coloring,
+            age_span: self.age_span,
+            trail_length: self.trail_length,
+            heat_map: self.heat_map,
+            heat_window: self.heat_window,
+            grid: self.grid,
+            rulers: self.rulers,
+        }
+    }
+
+    pub fn confirm_config(&self) -> ConfirmConfig {
+        ConfirmConfig {
+            clear: self.confirm_clear,
+            load: self.confirm_load,
+            quit: self.confirm_quit,
+            random_fill: self.confirm_random_fill,
+        }
+    }
+
+    pub fn animation_style(&self) -> Style {
+        Style {
+            cell_size: self.animation_cell_size,
+            gap: self.animation_gap,
+            ..Style::new(&self.theme)
+        }
+    }
+
+    pub fn svg_style(&self) -> SvgStyle {
+        SvgStyle {
+            merge_runs: self.svg_merge_runs,
+            grid: self.svg_grid,
+            ..SvgStyle::new(&self.theme)
+        }
+    }
+}
+
+/// Height of the top ruler and width of the left one, in points.
+const RULER_SIZE: f32 = 18.0;
+
+/// The pending confirmation as a modal dialog, over a backdrop that dims and
+/// blocks the rest of the window.
+fn show_confirm(ctx: &egui::Context, state: &mut State) {
+    let Some(confirm) = state.confirm else {
+        return;
+    };
+    let screen = ctx.screen_rect();
+    egui::Area::new("confirm backdrop")
+        .order(egui::Order::Foreground)
+        .fixed_pos(screen.min)
+        .show(ctx, |ui| {
+            ui.allocate_rect(screen, egui::Sense::click());
+            ui.painter()
+                .rect_filled(screen, 0.0, egui::Color32::from_black_alpha(120));
+        });
+
+    let id = egui::Id::new("confirm");
+    ctx.move_to_top(egui::LayerId::new(egui::Order::Foreground, id));
+    egui::Area::new(id)
+        .order(egui::Order::Foreground)
+        .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+        .show(ctx, |ui| {
+            egui::Frame::window(ui.style()).show(ui, |ui| {
+                ui.label(confirm.message());
+                ui.horizontal(|ui| {
+                    if ui.button(confirm.button()).clicked() {
+                        state.requested = Some(Action::Confirmed(confirm));
+                        state.confirm = None;
+                    }
+                    if ui.button("Cancel").clicked() {
+                        state.confirm = None;
+                    }
+                });
+            });
+        });
+}
+
+/// Smallest 1, 2 or 5 times a power of ten that is at least `min`.
+fn nice_step(min: f32) -> u32 {
+    let mut step = 1;
+    loop {
+        for factor in [1, 2, 5] {
+            if (step * factor) as f32 >= min {
+                return step * factor;
+            }
+        }
+        step *= 10;
+    }
+}
+
+/// Cell coordinates along the top and left edges of the window.
+fn draw_rulers(painter: &egui::Painter, ctx: &egui::Context, camera: &Camera, color: Rgb) {
+    let screen = ctx.screen_rect();
+    let points_per_cell = camera.zoom / ctx.pixels_per_point();
+    // Labels need about 50 points each.
+    let step = nice_step(50.0 / points_per_cell);
+    let background = egui::Color32::from_black_alpha(160);
+    let [r, g, b] = color.0;
+    let stroke = egui::Stroke::new(1.0, egui::Color32::from_rgb(r, g, b));
+    let text = egui::Color32::from_gray(200);
+    let font = egui::FontId::monospace(10.0);
+
+    let top = egui::Rect::from_min_max(screen.min, egui::pos2(screen.max.x, RULER_SIZE));
+    let left = egui::Rect::from_min_max(
+        egui::pos2(0.0, RULER_SIZE),
+        egui::pos2(RULER_SIZE * 2.0, screen.max.y),
+    );
+    painter.rect_filled(top, 0.0, background);
+    painter.rect_filled(left, 0.0, background);
+
+    // First labelled cell at or before the window edge, on each axis.
+    let ticks = |origin: f32, length: f32| {
+        let first = (origin / step as f32).floor() as i64 * step as i64;
+        let count = (length / points_per_cell / step as f32).ceil() as i64 + 1;
+        (0..=count).map(move |i| first + i * step as i64)
+    };
+    for cell in ticks(camera.position[0], screen.width()) {
+        let x = (cell as f32 - camera.position[0]) * points_per_cell;
+        if x < left.max.x {
+            continue;
+        }
+        painter.line_segment(
+            [egui::pos2(x, RULER_SIZE - 6.0), egui::pos2(x, RULER_SIZE)],
+            stroke,
+        );
+        painter.text(
+            egui::pos2(x + 2.0, 1.0),
+            egui::Align2::LEFT_TOP,
+            cell,
+            font.clone(),
+            text,
+        );
+    }
+    for cell in ticks(camera.position[1], screen.height()) {
+        let y = (cell as f32 - camera.position[1]) * points_per_cell;
+        if y < RULER_SIZE {
+            continue;
+        }
+        painter.line_segment(
+            [egui::pos2(left.max.x - 6.0, y), egui::pos2(left.max.x, y)],
+            stroke,
+        );
+        painter.text(
+            egui::pos2(1.0, y + 1.0),
+            egui::Align2::LEFT_TOP,
+            cell,
+            font.clone(),
+            text,
+        );
+    }
+}
+
+/// The minimap image with the visible area outlined. Clicking or dragging
+/// on it centers the view there.
+fn show_minimap(
+    ui: &mut egui::Ui,
+    ctx: &egui::Context,
+    minimap: &Minimap,
+    texture: egui::TextureId,
+    camera: &mut Camera,
+    outline: Rgb,
+) {
+    let points_per_pixel = minimap::MAX_SIZE as f32 / minimap.width.max(minimap.height) as f32;
+    let size = egui::vec2(minimap.width as f32, minimap.height as f32) * points_per_pixel;
+    let response = ui.add(egui::Image::new(texture, size).sense(egui::Sense::click_and_drag()));
+    let rect = response.rect;
+    let points_per_cell = points_per_pixel / minimap.scale as f32;
+
+    let viewport = ctx.screen_rect().size() * ctx.pixels_per_point();
+    let viewport = (viewport.x as u32, viewport.y as u32);
+    if response.clicked() || response.dragged() {
+        if let Some(pos) = response.interact_pointer_pos() {
+            let world = (pos - rect.min) / points_per_cell;
+            camera.center_on([world.x, world.y], viewport);
+        }
+    }
+
+    let view = egui::Rect::from_min_size(
+        rect.min + egui::vec2(camera.position[0], camera.position[1]) * points_per_cell,
+        egui::vec2(viewport.0 as f32, viewport.1 as f32) / camera.zoom * points_per_cell,
+    );
+    let [r, g, b] = outline.0;
+    ui.painter_at(rect).rect_stroke(
+        view,
+        0.0,
+        egui::Stroke::new(1.5, egui::Color32::from_rgb(r, g, b)),
+    );
 }
 
 pub struct GuiCtx {
     ctx: egui::Context,
+    minimap_texture: Option<egui::TextureHandle>,
     renderer: egui_wgpu::renderer::Renderer,
     state: egui_winit::State,
     screen_descriptor: egui_wgpu::renderer::ScreenDescriptor,
@@ -26,7 +435,8 @@ impl GuiCtx {
         let size = window.inner_size();
         Self {
             ctx: egui::Context::default(),
-            renderer: egui_wgpu::renderer::Renderer::new(&device, surface_cfg.format, None, 1),
+            minimap_texture: None,
+            renderer: egui_wgpu::renderer::Renderer::new(device, surface_cfg.format, None, 1),
             state: egui_winit::State::new(event_loop),
             screen_descriptor: egui_wgpu::renderer::ScreenDescriptor {
                 pixels_per_point: window.scale_factor() as f32,
@@ -42,17 +452,339 @@ impl GuiCtx {
     ) -> egui::FullOutput {
         let raw_input = self.state.take_egui_input(window);
 
+        if std::mem::take(&mut state.minimap_changed) {
+            if let Some(minimap) = &state.minimap {
+                let image = minimap.image(&state.theme);
+                // Nearest filtering keeps single cells crisp when zoomed in.
+                let options = egui::TextureOptions::NEAREST;
+                match &mut self.minimap_texture {
+                    Some(texture) => texture.set(image, options),
+                    None => {
+                        self.minimap_texture =
+                            Some(self.ctx.load_texture("minimap", image, options))
+                    }
+                }
+            }
+        }
+        let minimap_texture = self.minimap_texture.as_ref().map(|t| t.id());
+
         let full_output = self.ctx.run(raw_input, |ctx| {
-            egui::Window::new("Clear Color").show(ctx, |ui| {
-                ui.add(egui::Slider::new(&mut state.clear_color_r, 0.0..=1.0));
-                ui.add(egui::Slider::new(&mut state.clear_color_g, 0.0..=1.0));
-                ui.add(egui::Slider::new(&mut state.clear_color_b, 0.0..=1.0));
+            egui::TopBottomPanel::bottom("status").show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    match state.cursor_cell {
+                        Some((x, y)) => ui.label(format!("{x}, {y}")),
+                        None => ui.label("-"),
+                    };
+                    ui.separator();
+                    if let Some((x, y, width, height)) = state.selection {
+                        ui.label(format!("selection {width}x{height} at {x}, {y}"));
+                        ui.separator();
+                    }
+                    ui.label(format!("{:.1} px/cell", state.camera.zoom));
+                });
+            });
+
+            let painter = ctx.layer_painter(egui::LayerId::background());
+            let to_screen = |world: [f32; 2]| {
+                let camera = &state.camera;
+                egui::pos2(
+                    (world[0] - camera.position[0]) * camera.zoom / ctx.pixels_per_point(),
+                    (world[1] - camera.position[1]) * camera.zoom / ctx.pixels_per_point(),
+                )
+            };
+            let preview_color = match state.preview_erases {
+                true => state.theme.dead,
+                false => state.theme.alive,
+            };
+            let [r, g, b] = preview_color.0;
+            let preview_color = egui::Color32::from_rgb(r, g, b).gamma_multiply(0.7);
+            for &(x, y, length) in &state.preview {
+                let rect = egui::Rect::from_min_max(
+                    to_screen([x as f32, y as f32]),
+                    to_screen([(x + length) as f32, (y + 1) as f32]),
+                );
+                painter.rect_filled(rect, 0.0, preview_color);
+            }
+            if let Some((x, y, width, height)) = state.selection {
+                let rect = egui::Rect::from_min_max(
+                    to_screen([x as f32, y as f32]),
+                    to_screen([(x + width) as f32, (y + height) as f32]),
+                );
+                let [r, g, b] = state.theme.selection.0;
+                let color = egui::Color32::from_rgb(r, g, b);
+                painter.rect(
+                    rect,
+                    0.0,
+                    color.gamma_multiply(0.2),
+                    egui::Stroke::new(1.5, color),
+                );
+            }
+            if state.rulers {
+                draw_rulers(&painter, ctx, &state.camera, state.theme.grid);
+            }
+
+            let mut minimap_open = state.minimap_open;
+            egui::Window::new("Minimap")
+                .open(&mut minimap_open)
+                .resizable(false)
+                .show(ctx, |ui| {
+                    if let (Some(minimap), Some(texture)) = (&state.minimap, minimap_texture) {
+                        let outline = state.theme.selection;
+                        show_minimap(ui, ctx, minimap, texture, &mut state.camera, outline);
+                    }
+                });
+            state.minimap_open = minimap_open;
+            egui::Window::new("Keyboard shortcuts")
+                .open(&mut state.shortcuts_open)
+                .resizable(false)
+                .collapsible(false)
+                .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+                .show(ctx, |ui| {
+                    egui::Grid::new("shortcuts").striped(true).show(ui, |ui| {
+                        for (description, keys) in &state.shortcuts {
+                            ui.label(*description);
+                            ui.monospace(keys);
+                            ui.end_row();
+                        }
+                    });
+                });
+            egui::Window::new("Theme").show(ctx, |ui| {
+                let current = ThemePreset::ALL
+                    .into_iter()
+                    .find(|preset| preset.theme() == state.theme)
+                    .map_or("Custom", ThemePreset::name);
+                egui::ComboBox::from_label("preset")
+                    .selected_text(current)
+                    .show_ui(ui, |ui| {
+                        for preset in ThemePreset::ALL {
+                            let selected = preset.theme() == state.theme;
+                            if ui.selectable_label(selected, preset.name()).clicked() {
+                                state.theme = preset.theme();
+                            }
+                        }
+                    });
+
+                let theme = &mut state.theme;
+                egui::Grid::new("theme").show(ui, |ui| {
+                    for (label, color) in [
+                        ("alive", &mut theme.alive),
+                        ("young", &mut theme.young),
+                        ("dying", &mut theme.dying),
+                        ("dead", &mut theme.dead),
+                        ("grid", &mut theme.grid),
+                        ("selection", &mut theme.selection),
+                        ("heat", &mut theme.heat),
+                        ("background", &mut theme.background),
+                    ] {
+                        ui.label(label);
+                        ui.color_edit_button_srgb(&mut color.0);
+                        ui.end_row();
+                    }
+                });
+            });
+
+            egui::Window::new("Tools").show(ctx, |ui| {
+                ui.horizontal_wrapped(|ui| {
+                    for tool in Tool::ALL {
+                        ui.selectable_value(&mut state.tool, tool, tool.name());
+                    }
+                });
+                ui.checkbox(&mut state.fill_shapes, "Filled shapes");
+            });
+
+            egui::Window::new("Simulation").show(ctx, |ui| {
+                ui.label(format!("Generation {}", state.generation));
+                ui.add(egui::Slider::new(&mut state.ticks_per_sec, 1..=60).text("ticks/s"));
+                ui.checkbox(&mut state.edit_while_running, "Edit while running");
+                ui.horizontal(|ui| {
+                    ui.label("Rule");
+                    let field = ui.text_edit_singleline(&mut state.rule_text);
+                    let entered =
+                        field.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                    if ui.button("Apply").clicked() || entered {
+                        state.requested = Some(Action::ApplyRule);
+                    }
+                });
+                if let Some(error) = &state.rule_error {
+                    ui.colored_label(ui.visuals().error_fg_color, error);
+                }
+                ui.horizontal(|ui| {
+                    if ui.button("Reset to generation 0").clicked() {
+                        state.requested = Some(Action::ResetToGenerationZero);
+                    }
+                    if ui.button("Clear").clicked() {
+                        state.requested = Some(Action::Clear);
+                    }
+                });
+                ui.horizontal(|ui| {
+                    let target = match state.selection {
+                        Some(_) => "Random fill selection",
+                        None => "Random fill",
+                    };
+                    if ui.button(target).clicked() {
+                        state.requested = Some(Action::RandomFill);
+                    }
+                    ui.add(egui::Slider::new(&mut state.fill_density, 1..=100).suffix("% alive"));
+                });
             });
 
+            egui::Window::new("Session")
+                .default_open(false)
+                .show(ctx, |ui| {
+                    ui.text_edit_singleline(&mut state.session_path);
+                    ui.horizontal(|ui| {
+                        if ui.button("Save").clicked() {
+                            state.requested = Some(Action::SaveSession);
+                        }
+                        if ui.button("Load").clicked() {
+                            state.requested = Some(Action::LoadSession);
+                        }
+                    });
+                    if !state.status.is_empty() {
+                        ui.label(&state.status);
+                    }
+                });
+
+            egui::Window::new("Export")
+                .default_open(false)
+                .show(ctx, |ui| {
+                    ui.text_edit_singleline(&mut state.export_path);
+                    ui.horizontal(|ui| {
+                        ui.add(
+                            egui::DragValue::new(&mut state.export_scale)
+                                .clamp_range(1..=8)
+                                .suffix("x"),
+                        );
+                        if ui.button("Screenshot").clicked() {
+                            state.requested = Some(Action::Screenshot);
+                        }
+                    });
+
+                    ui.separator();
+                    ui.text_edit_singleline(&mut state.animation_path);
+                    let range = &mut state.animation_range;
+                    egui::Grid::new("animation").show(ui, |ui| {
+                        ui.label("generations");
+                        ui.horizontal(|ui| {
+                            ui.add(egui::DragValue::new(&mut range.from));
+                            ui.label("to");
+                            ui.add(egui::DragValue::new(&mut range.to));
+                            ui.label("every");
+                            ui.add(egui::DragValue::new(&mut range.step).clamp_range(1..=1000));
+                        });
+                        ui.end_row();
+
+                        ui.label("cell size");
+                        ui.horizontal(|ui| {
+                            ui.add(
+                                egui::DragValue::new(&mut state.animation_cell_size)
+                                    .clamp_range(1..=64)
+                                    .suffix(" px"),
+                            );
+                            ui.label("gap");
+                            ui.add(
+                                egui::DragValue::new(&mut state.animation_gap)
+                                    .clamp_range(0..=63)
+                                    .suffix(" px"),
+                            );
+                        });
+                        ui.end_row();
+
+                        ui.label("delay");
+                        ui.add(
+                            egui::DragValue::new(&mut state.animation_delay)
+                                .clamp_range(10..=10_000)
+                                .suffix(" ms"),
+                        );
+                        ui.end_row();
+                    });
+                    ui.checkbox(&mut state.animation_crop_to_view, "Only the visible area");
+                    if ui.button("Export animation").clicked() {
+                        state.requested = Some(Action::ExportAnimation);
+                    }
+
+                    ui.separator();
+                    ui.text_edit_singleline(&mut state.svg_path);
+                    ui.horizontal(|ui| {
+                        ui.radio_value(
+                            &mut state.svg_region,
+                            ExportRegion::LiveCells,
+                            "Live cells",
+                        );
+                        ui.radio_value(&mut state.svg_region, ExportRegion::View, "Visible area");
+                        ui.radio_value(&mut state.svg_region, ExportRegion::Board, "Whole board");
+                    });
+                    ui.horizontal(|ui| {
+                        ui.checkbox(&mut state.svg_merge_runs, "Merge runs");
+                        ui.checkbox(&mut state.svg_grid, "Grid lines");
+                    });
+                    if ui.button("Export SVG").clicked() {
+                        state.requested = Some(Action::ExportSvg);
+                    }
+                    if !state.export_status.is_empty() {
+                        ui.label(&state.export_status);
+                    }
+                });
+
             egui::Window::new("Cell").show(ctx, |ui| {
-                ui.add(egui::Slider::new(&mut state.cell_scale_factor, 5.0..=100.0));
-                ui.add(egui::Slider::new(&mut state.cell_offset, 1.0..=100.0));
+                ui.add(egui::Slider::new(&mut state.cell_gap, 0.0..=0.9).text("gap"));
+                ui.horizontal(|ui| {
+                    ui.radio_value(&mut state.coloring, Coloring::State, "By state");
+                    ui.radio_value(&mut state.coloring, Coloring::Age, "By age");
+                });
+                if state.coloring == Coloring::Age {
+                    ui.add(
+                        egui::Slider::new(&mut state.age_span, 1..=10_000)
+                            .logarithmic(true)
+                            .text("generations to full age"),
+                    );
+                }
+                ui.add(egui::Slider::new(&mut state.trail_length, 0..=100).text("trails"));
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut state.grid, "Grid lines");
+                    ui.checkbox(&mut state.rulers, "Rulers");
+                    ui.checkbox(&mut state.minimap_open, "Minimap");
+                });
+                ui.checkbox(&mut state.heat_map, "Heat map");
+                if state.heat_map {
+                    ui.add(
+                        egui::Slider::new(&mut state.heat_window, 2..=10_000)
+                            .logarithmic(true)
+                            .text("generations averaged"),
+                    );
+                }
+                if ui.button("Fit pattern to window").clicked() {
+                    state.requested = Some(Action::FitPattern);
+                }
+                if ui.button("Toggle fullscreen").clicked() {
+                    state.requested = Some(Action::ToggleFullscreen);
+                }
             });
+
+            egui::Window::new("Settings")
+                .default_open(false)
+                .show(ctx, |ui| {
+                    if let Some(path) = Config::path() {
+                        ui.label(format!("Saved to {}", path.display()));
+                    }
+                    if ui.button("Reset to defaults").clicked() {
+                        state.requested = Some(Action::ResetSettings);
+                    }
+                    if ui.button("Keyboard shortcuts").clicked() {
+                        state.shortcuts_open = true;
+                    }
+                    ui.separator();
+                    ui.label("Ask before");
+                    ui.checkbox(&mut state.confirm_clear, "clearing the board");
+                    ui.checkbox(&mut state.confirm_load, "loading over unsaved changes");
+                    ui.checkbox(&mut state.confirm_quit, "quitting with unsaved changes");
+                    ui.checkbox(
+                        &mut state.confirm_random_fill,
+                        "randomly filling a large area",
+                    );
+                });
+
+            show_confirm(ctx, state);
         });
 
         let platform_output = full_output.platform_output.clone();
@@ -82,7 +814,7 @@ impl GuiCtx {
 
         for (tex_id, img_delta) in output.textures_delta.set {
             self.renderer
-                .update_texture(&device, &queue, tex_id, &img_delta);
+                .update_texture(device, queue, tex_id, &img_delta);
         }
 
         for tex_id in output.textures_delta.free {
@@ -92,6 +824,15 @@ impl GuiCtx {
         clipped_primitives
     }
 
+    /// Keeps egui's idea of the output size and DPI in sync with the window.
+    pub fn resize(&mut self, size: winit::dpi::PhysicalSize<u32>, scale_factor: f64) {
+        self.state.set_pixels_per_point(scale_factor as f32);
+        self.screen_descriptor = egui_wgpu::renderer::ScreenDescriptor {
+            pixels_per_point: scale_factor as f32,
+            size_in_pixels: [size.width, size.height],
+        };
+    }
+
     pub fn renderer(&self) -> &egui_wgpu::Renderer {
         &self.renderer
     }
@@ -0,0 +1,397 @@
+use std::collections::HashMap;
+use std::fmt::{self, Write as _};
+use std::io::Write as _;
+use std::time::Instant;
+
+use crate::animation::{Animation, AnimationError, FrameRange, Style};
+use crate::camera::Camera;
+use crate::cli::{AnimateArgs, FramesArgs, RunArgs, SvgArgs};
+use crate::config::{Config, DisplayConfig};
+use crate::gpu::compute::LifeCompute;
+use crate::gpu::offscreen::{self, Image, Offscreen};
+use crate::pattern::{Pattern, PatternError};
+use crate::svg::{self, SvgStyle};
+use crate::theme::{Coloring, ThemePreset};
+use crate::universe::{Backend, Universe};
+
+#[derive(Debug)]
+pub enum RunError {
+    Pattern(PatternError),
+    Gpu(String),
+    /// The board doesn't fit the GPU's buffer limits.
+    GpuLimits(String),
+    Diverged {
+        generation: u64,
+    },
+    Render(String),
+    Png(png::EncodingError),
+    Animation(AnimationError),
+}
+
+impl fmt::Display for RunError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RunError::Pattern(e) => write!(f, "{e}"),
+            RunError::Gpu(e) => write!(f, "no usable GPU: {e}"),
+            RunError::GpuLimits(e) => write!(f, "{e}"),
+            RunError::Diverged { generation } => {
+                write!(f, "GPU and CPU engines diverged at generation {generation}")
+            }
+            RunError::Render(e) => write!(f, "{e}"),
+            RunError::Png(e) => write!(f, "couldn't write PNG: {e}"),
+            RunError::Animation(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl From<PatternError> for RunError {
+    fn from(e: PatternError) -> Self {
+        RunError::Pattern(e)
+    }
+}
+
+impl From<std::io::Error> for RunError {
+    fn from(e: std::io::Error) -> Self {
+        RunError::Pattern(e.into())
+    }
+}
+
+enum StopReason {
+    Limit,
+    DiedOut,
+    Repeating { period: u64 },
+}
+
+/// Generations recorded into one GPU command buffer.
+const GENERATIONS_PER_SUBMIT: u32 = 1024;
+
+struct GpuEngine {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    life: LifeCompute,
+}
+
+impl GpuEngine {
+    fn new(universe: &Universe, force_fallback_adapter: bool) -> Result<Self, RunError> {
+        let (device, queue) =
+            pollster::block_on(crate::gpu::headless_device(force_fallback_adapter))
+                .map_err(RunError::Gpu)?;
+        let life = LifeCompute::new(&device, universe).map_err(RunError::GpuLimits)?;
+        Ok(Self {
+            device,
+            queue,
+            life,
+        })
+    }
+
+    /// Submits `generations` steps, a batch of at most
+    /// `GENERATIONS_PER_SUBMIT` at a time so command buffers stay small.
+    fn step(&mut self, generations: u64) {
+        let mut left = generations;
+        while left > 0 {
+            let batch = left.min(GENERATIONS_PER_SUBMIT as u64) as u32;
+            let mut encoder = self
+                .device
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+            self.life.step(&mut encoder, batch);
+            self.queue.submit(std::iter::once(encoder.finish()));
+            left -= batch as u64;
+        }
+    }
+}
+
+/// Display settings from the config file, with `theme` and `coloring`
+/// replacing the saved ones when given.
+fn display(theme: Option<ThemePreset>, coloring: Option<Coloring>) -> DisplayConfig {
+    let mut display = Config::load().display;
+    if let Some(preset) = theme {
+        display.theme = preset.theme();
+    }
+    if let Some(coloring) = coloring {
+        display.coloring = coloring;
+    }
+    display
+}
+
+/// Renders `universe` for --png, reusing the GPU backend's device when there
+/// is one. Its board is already up to date, since runs end with a readback.
+fn render_png(
+    universe: &Universe,
+    gpu: Option<&GpuEngine>,
+    args: &RunArgs,
+) -> Result<Image, RunError> {
+    let size = (args.png_width, args.png_height);
+    let mut camera = Camera::default();
+    camera.fit(
+        universe
+            .bounding_box()
+            .unwrap_or((0, 0, universe.width(), universe.height())),
+        size,
+    );
+
+    let fresh;
+    let engine = match gpu {
+        Some(engine) => engine,
+        None => {
+            fresh = GpuEngine::new(universe, args.fallback_adapter)?;
+            &fresh
+        }
+    };
+    offscreen::render(
+        &engine.device,
+        &engine.queue,
+        &engine.life,
+        &camera,
+        size,
+        &display(args.theme, args.coloring),
+    )
+    .map_err(RunError::Render)
+}
+
+pub fn run(args: RunArgs) -> Result<(), RunError> {
+    let input = Pattern::load(&args.input)?;
+    let mut universe = args.universe.universe_with(&input, None)?;
+
+    let mut gpu = match args.backend {
+        Backend::Cpu => None,
+        Backend::Gpu => Some(GpuEngine::new(&universe, args.fallback_adapter)?),
+    };
+    // With --verify the CPU engine runs alongside as a reference.
+    let mut reference = (gpu.is_some() && args.verify).then(|| universe.clone());
+    // Reading the board back is what makes the GPU backend slow, so only do
+    // it every generation when something needs to look at it.
+    let inspect_every_generation = !args.no_stop || reference.is_some();
+
+    let start = Instant::now();
+    let mut seen = HashMap::from([(universe.state_hash(), 0)]);
+    let mut reason = StopReason::Limit;
+
+    while universe.generation() < args.gens {
+        match &mut gpu {
+            None => universe.tick(),
+            Some(gpu) if inspect_every_generation => {
+                gpu.step(1);
+                universe.set_generation(universe.generation() + 1);
+                gpu.life.download(&gpu.device, &gpu.queue, &mut universe);
+            }
+            Some(gpu) => {
+                let remaining = args.gens - universe.generation();
+                gpu.step(remaining);
+                universe.set_generation(args.gens);
+                gpu.life.download(&gpu.device, &gpu.queue, &mut universe);
+            }
+        }
+        if let Some(reference) = &mut reference {
+            reference.tick();
+            let ages_match = (reference.cells.iter().zip(&universe.cells))
+                .all(|(a, b)| a.changed_at == b.changed_at);
+            if reference.state_hash() != universe.state_hash() || !ages_match {
+                return Err(RunError::Diverged {
+                    generation: universe.generation(),
+                });
+            }
+        }
+        if args.no_stop {
+            continue;
+        }
+        if universe.population() == 0 {
+            reason = StopReason::DiedOut;
+            break;
+        }
+        if let Some(prev) = seen.insert(universe.state_hash(), universe.generation()) {
+            reason = StopReason::Repeating {
+                period: universe.generation() - prev,
+            };
+            break;
+        }
+    }
+    let elapsed = start.elapsed();
+
+    let mut output = Pattern::from_universe(&universe);
+    output.name = input.name;
+    output
+        .comments
+        .push(format!("Generation {}", universe.generation()));
+
+    let mut summary = String::new();
+    writeln!(summary, "rule: {}", universe.rule).unwrap();
+    let backend = match args.backend {
+        Backend::Cpu => "cpu",
+        Backend::Gpu => "gpu",
+    };
+    writeln!(summary, "backend: {backend}").unwrap();
+    if reference.is_some() {
+        writeln!(summary, "verified: every generation matches the CPU engine").unwrap();
+    }
+    writeln!(summary, "generations: {}", universe.generation()).unwrap();
+    writeln!(summary, "population: {}", universe.population()).unwrap();
+    match universe.bounding_box() {
+        Some((x, y, w, h)) => writeln!(summary, "bounding box: {w}x{h} at ({x}, {y})").unwrap(),
+        None => writeln!(summary, "bounding box: empty").unwrap(),
+    }
+    match reason {
+        StopReason::Limit => writeln!(summary, "stopped: generation limit").unwrap(),
+        StopReason::DiedOut => writeln!(summary, "stopped: died out").unwrap(),
+        StopReason::Repeating { period } => {
+            writeln!(summary, "stopped: repeating with period {period}").unwrap()
+        }
+    }
+    writeln!(summary, "elapsed: {:.3}s", elapsed.as_secs_f64()).unwrap();
+
+    match &args.out {
+        Some(path) => output.save(path)?,
+        None => print!("{}", output.to_rle()),
+    }
+    match (&args.summary, &args.out) {
+        (Some(path), _) => std::fs::write(path, summary)?,
+        (None, Some(_)) => print!("{summary}"),
+        (None, None) => eprint!("{summary}"),
+    }
+    std::io::stdout().flush()?;
+
+    if let Some(path) = &args.png {
+        render_png(&universe, gpu.as_ref(), &args)?
+            .save_png(path)
+            .map_err(RunError::Png)?;
+    }
+
+    Ok(())
+}
+
+pub fn animate(args: AnimateArgs) -> Result<(), RunError> {
+    let input = Pattern::load(&args.input)?;
+    let mut universe = args.universe.universe_with(&input, None)?;
+
+    let range = FrameRange {
+        from: args.from,
+        to: args.to,
+        step: args.step,
+    };
+    let animation = Animation::record(&mut universe, range);
+    let theme = display(args.theme, None).theme;
+    let style = Style {
+        cell_size: args.cell_size,
+        gap: args.gap,
+        alive: args.alive_color.unwrap_or(theme.alive),
+        dead: args.dead_color.unwrap_or(theme.dead),
+        background: args.background_color.unwrap_or(theme.background),
+    };
+    let crop = args.crop.unwrap_or_else(|| animation.fit_crop());
+    animation
+        .save(&args.out, crop, &style, args.delay)
+        .map_err(RunError::Animation)?;
+
+    eprintln!(
+        "wrote {} frames of {}x{} cells to {}",
+        animation.frame_count(),
+        crop.width,
+        crop.height,
+        args.out.display()
+    );
+    Ok(())
+}
+
+pub fn frames(args: FramesArgs) -> Result<(), RunError> {
+    let input = Pattern::load(&args.input)?;
+    let mut universe = args.universe.universe_with(&input, None)?;
+    universe.set_heat_window(args.heat_map);
+
+    let mut engine = GpuEngine::new(&universe, args.fallback_adapter)?;
+    // The engine starts with the whole board, so nothing is pending.
+    universe.take_dirty_rows();
+
+    let size = (args.frame_width, args.frame_height);
+    let crop = args.crop.map(|c| (c.x, c.y, c.width, c.height)).unwrap_or((
+        0,
+        0,
+        universe.width(),
+        universe.height(),
+    ));
+    let mut camera = Camera::default();
+    camera.fit(crop, size);
+    let mut display = display(args.theme, args.coloring);
+    if let Some(trails) = args.trails {
+        display.trail_length = trails;
+    }
+    display.heat_map = args.heat_map.is_some();
+    let target = Offscreen::new(&engine.device, &engine.life, &camera, size, &display)
+        .map_err(RunError::Render)?;
+
+    match &args.out_dir {
+        Some(dir) => std::fs::create_dir_all(dir)?,
+        None => eprintln!(
+            "piping {w}x{h} RGBA frames, e.g. into \
+             ffmpeg -f rawvideo -pix_fmt rgba -s {w}x{h} -r 30 -i - out.mp4",
+            w = size.0,
+            h = size.1
+        ),
+    }
+
+    let mut stdout = std::io::stdout().lock();
+    let frames = args.gens / args.per_frame as u64;
+    for frame in 0..=frames {
+        if frame > 0 {
+            match args.backend {
+                Backend::Gpu => engine.step(args.per_frame as u64),
+                Backend::Cpu => {
+                    for _ in 0..args.per_frame {
+                        universe.tick();
+                    }
+                    engine.life.write_params(&engine.queue, &universe);
+                    for rows in universe.take_dirty_rows() {
+                        engine.life.upload_rows(&engine.queue, &universe, rows);
+                    }
+                }
+            }
+        }
+
+        let image = target.render(&engine.device, &engine.queue, &engine.life);
+        match &args.out_dir {
+            Some(dir) => image
+                .save_png(&dir.join(format!("frame-{frame:05}.png")))
+                .map_err(RunError::Png)?,
+            None => stdout.write_all(&image.pixels)?,
+        }
+    }
+    stdout.flush()?;
+
+    eprintln!(
+        "rendered {} frames, {} generations apart",
+        frames + 1,
+        args.per_frame
+    );
+    Ok(())
+}
+
+pub fn svg(args: SvgArgs) -> Result<(), RunError> {
+    let mut pattern = Pattern::load(&args.input)?;
+    if let Some(crop) = args.crop {
+        pattern.cells.retain(|&(x, y)| {
+            x >= crop.x && y >= crop.y && x - crop.x < crop.width && y - crop.y < crop.height
+        });
+        for cell in pattern.cells.iter_mut() {
+            *cell = (cell.0 - crop.x, cell.1 - crop.y);
+        }
+        pattern.width = crop.width;
+        pattern.height = crop.height;
+    }
+    if args.title.is_some() {
+        pattern.name = args.title;
+    }
+
+    let theme = display(args.theme, None).theme;
+    let style = SvgStyle {
+        cell_size: args.cell_size,
+        merge_runs: !args.no_merge,
+        grid: args.grid,
+        alive: args.alive_color.unwrap_or(theme.alive),
+        background: (!args.transparent).then(|| args.background_color.unwrap_or(theme.background)),
+        grid_color: args.grid_color.unwrap_or(theme.grid),
+    };
+    let document = svg::render(&pattern, &style);
+    match &args.out {
+        Some(path) => std::fs::write(path, document)?,
+        None => print!("{document}"),
+    }
+    Ok(())
+}
@@ -0,0 +1,203 @@
+use std::collections::VecDeque;
+
+use crate::cell::CellState;
+use crate::universe::Universe;
+
+/// Most cell changes kept for undoing and redoing, across every edit. Each
+/// takes 12 bytes, so this is about 50 MB.
+const MAX_CELLS: usize = 1 << 22;
+
+/// Cells changed by one edit, as `(position, before, after)`.
+type Edit = Vec<((u32, u32), CellState, CellState)>;
+
+/// Board edits that can be undone and redone. Undoing puts the cells an edit
+/// touched back the way they were before it, whatever the generations since
+/// did to them, and leaves every other cell alone.
+///
+/// The oldest edits are forgotten once finished edits hold more than
+/// `max_cells` cell changes. An edit larger than that on its own can't be
+/// undone.
+pub struct History {
+    undo: VecDeque<Edit>,
+    redo: Vec<Edit>,
+    /// Cell changes held by `undo` and `redo` together.
+    cells: usize,
+    max_cells: usize,
+    /// The current edit grew past `max_cells` and is no longer recorded.
+    dropped: bool,
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self::with_max_cells(MAX_CELLS)
+    }
+}
+
+impl History {
+    fn with_max_cells(max_cells: usize) -> Self {
+        Self {
+            undo: VecDeque::new(),
+            redo: Vec::new(),
+            cells: 0,
+            max_cells,
+            dropped: false,
+        }
+    }
+
+    /// Starts a new edit; changes recorded until the next `begin` are undone
+    /// together.
+    pub fn begin(&mut self) {
+        if !self.dropped && self.undo.back().is_some_and(|edit| edit.is_empty()) {
+            return;
+        }
+        while self.cells > self.max_cells {
+            let oldest = self.undo.pop_front().unwrap();
+            self.cells -= oldest.len();
+        }
+        self.dropped = false;
+        self.undo.push_back(Edit::new());
+    }
+
+    /// Sets a cell as part of the current edit.
+    pub fn set(&mut self, universe: &mut Universe, (x, y): (u32, u32), state: CellState) {
+        let before = universe.get(x, y);
+        if before == state {
+            return;
+        }
+        universe.set(x, y, state);
+        if !self.redo.is_empty() {
+            self.cells -= self.redo.iter().map(Vec::len).sum::<usize>();
+            self.redo.clear();
+        }
+        if self.dropped {
+            return;
+        }
+        if self.undo.is_empty() {
+            self.undo.push_back(Edit::new());
+        }
+        let edit = self.undo.back_mut().unwrap();
+        edit.push(((x, y), before, state));
+        self.cells += 1;
+
+        if edit.len() > self.max_cells {
+            self.cells -= edit.len();
+            self.undo.pop_back();
+            self.dropped = true;
+        }
+    }
+
+    /// Reverts the last edit that changed anything. Returns whether there
+    /// was one.
+    pub fn undo(&mut self, universe: &mut Universe) -> bool {
+        while let Some(edit) = self.undo.pop_back() {
+            if edit.is_empty() {
+                continue;
+            }
+            for &((x, y), before, _) in edit.iter().rev() {
+                universe.set(x, y, before);
+            }
+            self.redo.push(edit);
+            return true;
+        }
+        false
+    }
+
+    /// Applies the last undone edit again. Returns whether there was one.
+    pub fn redo(&mut self, universe: &mut Universe) -> bool {
+        let Some(edit) = self.redo.pop() else {
+            return false;
+        };
+        for &((x, y), _, after) in edit.iter() {
+            universe.set(x, y, after);
+        }
+        self.undo.push_back(edit);
+        true
+    }
+
+    /// Forgets every edit, for when the board is replaced.
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+        self.cells = 0;
+        self.dropped = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn alive(universe: &Universe) -> Vec<(u32, u32)> {
+        let cells = universe.cells.iter().filter(|c| c.state.is_alive());
+        cells.map(|c| (c.x, c.y)).collect()
+    }
+
+    /// Records an edit bringing `cells` to life.
+    fn draw(history: &mut History, universe: &mut Universe, cells: &[(u32, u32)]) {
+        history.begin();
+        for &cell in cells {
+            history.set(universe, cell, CellState::Alive);
+        }
+    }
+
+    #[test]
+    fn oldest_edits_are_forgotten_past_the_cell_limit() {
+        let mut universe = Universe::new(8, 8, Default::default());
+        let mut history = History::with_max_cells(4);
+        draw(&mut history, &mut universe, &[(0, 0), (1, 0)]);
+        draw(&mut history, &mut universe, &[(0, 1), (1, 1)]);
+        draw(&mut history, &mut universe, &[(0, 2)]);
+        draw(&mut history, &mut universe, &[(0, 3)]);
+
+        for _ in 0..3 {
+            assert!(history.undo(&mut universe));
+        }
+        assert!(!history.undo(&mut universe));
+        assert_eq!(alive(&universe), [(0, 0), (1, 0)]);
+    }
+
+    #[test]
+    fn edits_larger_than_the_limit_are_not_recorded() {
+        let mut universe = Universe::new(8, 8, Default::default());
+        let mut history = History::with_max_cells(4);
+        draw(&mut history, &mut universe, &[(0, 0)]);
+        draw(
+            &mut history,
+            &mut universe,
+            &[(0, 1), (1, 1), (2, 1), (3, 1), (4, 1)],
+        );
+        draw(&mut history, &mut universe, &[(0, 2)]);
+
+        assert!(history.undo(&mut universe));
+        assert_eq!(alive(&universe).len(), 6);
+        assert!(history.undo(&mut universe));
+        assert!(!history.undo(&mut universe));
+        assert_eq!(alive(&universe), [(0, 1), (1, 1), (2, 1), (3, 1), (4, 1)]);
+    }
+
+    #[test]
+    fn undo_and_redo_after_generations() {
+        // A blinker drawn outside the history keeps running throughout.
+        let mut universe = Universe::new(10, 10, Default::default());
+        for x in 5..8 {
+            universe.set(x, 6, CellState::Alive);
+        }
+        let mut history = History::default();
+        let block = [(1, 1), (2, 1), (1, 2), (2, 2)];
+        draw(&mut history, &mut universe, &block);
+        for _ in 0..3 {
+            universe.tick();
+        }
+        let blinker = [(6, 5), (6, 6), (6, 7)];
+
+        assert!(history.undo(&mut universe));
+        assert_eq!(alive(&universe), blinker);
+        universe.tick();
+        assert!(history.redo(&mut universe));
+        let mut expected = block.to_vec();
+        expected.extend([(5, 6), (6, 6), (7, 6)]);
+        expected.sort_by_key(|&(x, y)| (y, x));
+        assert_eq!(alive(&universe), expected);
+        assert!(!history.redo(&mut universe));
+    }
+}
@@ -0,0 +1,418 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+use egui_winit::winit;
+use serde::{Deserialize, Serialize};
+use winit::event::{ModifiersState, VirtualKeyCode};
+
+/// Something a keyboard shortcut can do.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum InputAction {
+    Start,
+    Pause,
+    ToggleRunning,
+    Step,
+    Clear,
+    ResetToGenerationZero,
+    ZoomIn,
+    ZoomOut,
+    PanLeft,
+    PanRight,
+    PanUp,
+    PanDown,
+    FitPattern,
+    ToggleFullscreen,
+    ClearSelection,
+    Copy,
+    Paste,
+    Undo,
+    Redo,
+    ShowShortcuts,
+}
+
+impl InputAction {
+    pub const ALL: [InputAction; 20] = [
+        InputAction::Start,
+        InputAction::Pause,
+        InputAction::ToggleRunning,
+        InputAction::Step,
+        InputAction::Clear,
+        InputAction::ResetToGenerationZero,
+        InputAction::ZoomIn,
+        InputAction::ZoomOut,
+        InputAction::PanLeft,
+        InputAction::PanRight,
+        InputAction::PanUp,
+        InputAction::PanDown,
+        InputAction::FitPattern,
+        InputAction::ToggleFullscreen,
+        InputAction::ClearSelection,
+        InputAction::Copy,
+        InputAction::Paste,
+        InputAction::Undo,
+        InputAction::Redo,
+        InputAction::ShowShortcuts,
+    ];
+
+    pub fn description(self) -> &'static str {
+        match self {
+            InputAction::Start => "Start",
+            InputAction::Pause => "Pause",
+            InputAction::ToggleRunning => "Start or pause",
+            InputAction::Step => "Advance one generation",
+            InputAction::Clear => "Clear all cells",
+            InputAction::ResetToGenerationZero => "Reset to generation 0",
+            InputAction::ZoomIn => "Zoom in",
+            InputAction::ZoomOut => "Zoom out",
+            InputAction::PanLeft => "Pan left",
+            InputAction::PanRight => "Pan right",
+            InputAction::PanUp => "Pan up",
+            InputAction::PanDown => "Pan down",
+            InputAction::FitPattern => "Fit the pattern to the window",
+            InputAction::ToggleFullscreen => "Toggle fullscreen",
+            InputAction::ClearSelection => "Clear the selection",
+            InputAction::Copy => "Copy the selection",
+            InputAction::Paste => "Paste at the cursor",
+            InputAction::Undo => "Undo the last edit",
+            InputAction::Redo => "Redo the last undone edit",
+            InputAction::ShowShortcuts => "Show keyboard shortcuts",
+        }
+    }
+
+    fn default_keys(self) -> &'static [&'static str] {
+        match self {
+            InputAction::Start => &["S"],
+            InputAction::Pause => &["P"],
+            InputAction::ToggleRunning => &["Space"],
+            InputAction::Step => &["N"],
+            InputAction::Clear => &["R"],
+            InputAction::ResetToGenerationZero => &["Ctrl+R"],
+            InputAction::ZoomIn => &["Equals", "NumpadAdd"],
+            InputAction::ZoomOut => &["Minus", "NumpadSubtract"],
+            InputAction::PanLeft => &["Left"],
+            InputAction::PanRight => &["Right"],
+            InputAction::PanUp => &["Up"],
+            InputAction::PanDown => &["Down"],
+            InputAction::FitPattern => &["F"],
+            InputAction::ToggleFullscreen => &["F11"],
+            InputAction::ClearSelection => &["Escape"],
+            InputAction::Copy => &["Ctrl+C"],
+            InputAction::Paste => &["Ctrl+V"],
+            InputAction::Undo => &["Ctrl+Z"],
+            InputAction::Redo => &["Ctrl+Shift+Z", "Ctrl+Y"],
+            InputAction::ShowShortcuts => &["F1"],
+        }
+    }
+}
+
+/// Key names accepted in the config file. Letters and digits are looked up
+/// separately.
+const KEY_NAMES: &[(&str, VirtualKeyCode)] = &[
+    ("F1", VirtualKeyCode::F1),
+    ("F2", VirtualKeyCode::F2),
+    ("F3", VirtualKeyCode::F3),
+    ("F4", VirtualKeyCode::F4),
+    ("F5", VirtualKeyCode::F5),
+    ("F6", VirtualKeyCode::F6),
+    ("F7", VirtualKeyCode::F7),
+    ("F8", VirtualKeyCode::F8),
+    ("F9", VirtualKeyCode::F9),
+    ("F10", VirtualKeyCode::F10),
+    ("F11", VirtualKeyCode::F11),
+    ("F12", VirtualKeyCode::F12),
+    ("Left", VirtualKeyCode::Left),
+    ("Right", VirtualKeyCode::Right),
+    ("Up", VirtualKeyCode::Up),
+    ("Down", VirtualKeyCode::Down),
+    ("Space", VirtualKeyCode::Space),
+    ("Escape", VirtualKeyCode::Escape),
+    ("Enter", VirtualKeyCode::Return),
+    ("Tab", VirtualKeyCode::Tab),
+    ("Backspace", VirtualKeyCode::Back),
+    ("Delete", VirtualKeyCode::Delete),
+    ("Insert", VirtualKeyCode::Insert),
+    ("Home", VirtualKeyCode::Home),
+    ("End", VirtualKeyCode::End),
+    ("PageUp", VirtualKeyCode::PageUp),
+    ("PageDown", VirtualKeyCode::PageDown),
+    ("Equals", VirtualKeyCode::Equals),
+    ("Plus", VirtualKeyCode::Plus),
+    ("Minus", VirtualKeyCode::Minus),
+    ("Comma", VirtualKeyCode::Comma),
+    ("Period", VirtualKeyCode::Period),
+    ("Slash", VirtualKeyCode::Slash),
+    ("Backslash", VirtualKeyCode::Backslash),
+    ("Semicolon", VirtualKeyCode::Semicolon),
+    ("Apostrophe", VirtualKeyCode::Apostrophe),
+    ("LBracket", VirtualKeyCode::LBracket),
+    ("RBracket", VirtualKeyCode::RBracket),
+    ("Grave", VirtualKeyCode::Grave),
+    ("NumpadAdd", VirtualKeyCode::NumpadAdd),
+    ("NumpadSubtract", VirtualKeyCode::NumpadSubtract),
+    ("NumpadMultiply", VirtualKeyCode::NumpadMultiply),
+    ("NumpadDivide", VirtualKeyCode::NumpadDivide),
+    ("NumpadEnter", VirtualKeyCode::NumpadEnter),
+];
+
+const LETTERS: [VirtualKeyCode; 26] = [
+    VirtualKeyCode::A,
+    VirtualKeyCode::B,
+    VirtualKeyCode::C,
+    VirtualKeyCode::D,
+    VirtualKeyCode::E,
+    VirtualKeyCode::F,
+    VirtualKeyCode::G,
+    VirtualKeyCode::H,
+    VirtualKeyCode::I,
+    VirtualKeyCode::J,
+    VirtualKeyCode::K,
+    VirtualKeyCode::L,
+    VirtualKeyCode::M,
+    VirtualKeyCode::N,
+    VirtualKeyCode::O,
+    VirtualKeyCode::P,
+    VirtualKeyCode::Q,
+    VirtualKeyCode::R,
+    VirtualKeyCode::S,
+    VirtualKeyCode::T,
+    VirtualKeyCode::U,
+    VirtualKeyCode::V,
+    VirtualKeyCode::W,
+    VirtualKeyCode::X,
+    VirtualKeyCode::Y,
+    VirtualKeyCode::Z,
+];
+
+const DIGITS: [VirtualKeyCode; 10] = [
+    VirtualKeyCode::Key0,
+    VirtualKeyCode::Key1,
+    VirtualKeyCode::Key2,
+    VirtualKeyCode::Key3,
+    VirtualKeyCode::Key4,
+    VirtualKeyCode::Key5,
+    VirtualKeyCode::Key6,
+    VirtualKeyCode::Key7,
+    VirtualKeyCode::Key8,
+    VirtualKeyCode::Key9,
+];
+
+/// A key with the modifiers that must be held with it, written like
+/// `Ctrl+Shift+Z`. Modifiers must match exactly, so `R` and `Ctrl+R` can do
+/// different things.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KeyCombo {
+    pub key: VirtualKeyCode,
+    pub ctrl: bool,
+    pub shift: bool,
+    pub alt: bool,
+}
+
+impl KeyCombo {
+    fn matches(&self, key: VirtualKeyCode, modifiers: ModifiersState) -> bool {
+        self.key == key
+            && self.ctrl == modifiers.ctrl()
+            && self.shift == modifiers.shift()
+            && self.alt == modifiers.alt()
+    }
+}
+
+fn parse_key(name: &str) -> Option<VirtualKeyCode> {
+    let mut chars = name.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        let c = c.to_ascii_uppercase();
+        if c.is_ascii_uppercase() {
+            return Some(LETTERS[(c as u8 - b'A') as usize]);
+        }
+        if c.is_ascii_digit() {
+            return Some(DIGITS[(c as u8 - b'0') as usize]);
+        }
+    }
+    KEY_NAMES
+        .iter()
+        .find(|(n, _)| n.eq_ignore_ascii_case(name))
+        .map(|&(_, key)| key)
+}
+
+fn key_name(key: VirtualKeyCode) -> String {
+    if let Some(i) = LETTERS.iter().position(|&k| k == key) {
+        return char::from(b'A' + i as u8).to_string();
+    }
+    if let Some(i) = DIGITS.iter().position(|&k| k == key) {
+        return i.to_string();
+    }
+    match KEY_NAMES.iter().find(|&&(_, k)| k == key) {
+        Some((name, _)) => name.to_string(),
+        None => format!("{key:?}"),
+    }
+}
+
+impl FromStr for KeyCombo {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts: Vec<&str> = s.split('+').map(str::trim).collect();
+        // Two trailing empty parts mean the key itself is `+`, while one is
+        // a modifier with no key after it.
+        if parts.len() > 1 && parts[parts.len() - 2..] == ["", ""] {
+            parts.pop();
+            *parts.last_mut().unwrap() = "Plus";
+        }
+        let (key, modifiers) = parts.split_last().unwrap();
+        let key = parse_key(key).ok_or_else(|| format!("unknown key '{key}' in '{s}'"))?;
+        let mut combo = KeyCombo {
+            key,
+            ctrl: false,
+            shift: false,
+            alt: false,
+        };
+        for modifier in modifiers {
+            match modifier.to_ascii_lowercase().as_str() {
+                "ctrl" | "control" => combo.ctrl = true,
+                "shift" => combo.shift = true,
+                "alt" => combo.alt = true,
+                _ => return Err(format!("unknown modifier '{modifier}' in '{s}'")),
+            }
+        }
+        Ok(combo)
+    }
+}
+
+impl fmt::Display for KeyCombo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.ctrl {
+            write!(f, "Ctrl+")?;
+        }
+        if self.shift {
+            write!(f, "Shift+")?;
+        }
+        if self.alt {
+            write!(f, "Alt+")?;
+        }
+        write!(f, "{}", key_name(self.key))
+    }
+}
+
+impl Serialize for KeyCombo {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for KeyCombo {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// Keys bound to each action. Bindings from the config file replace the
+/// defaults of the actions they name, leaving the others alone.
+pub struct Keymap {
+    bindings: Vec<(InputAction, Vec<KeyCombo>)>,
+}
+
+impl Keymap {
+    pub fn new(overrides: &BTreeMap<InputAction, Vec<KeyCombo>>) -> Self {
+        let bindings = InputAction::ALL
+            .iter()
+            .map(|&action| {
+                let keys = match overrides.get(&action) {
+                    Some(keys) => keys.clone(),
+                    None => action
+                        .default_keys()
+                        .iter()
+                        .map(|key| key.parse().unwrap())
+                        .collect(),
+                };
+                (action, keys)
+            })
+            .collect();
+        Self { bindings }
+    }
+
+    /// The action bound to `key` pressed with `modifiers`. When a key is
+    /// bound twice, the action listed first in [`InputAction::ALL`] wins.
+    pub fn action(&self, key: VirtualKeyCode, modifiers: ModifiersState) -> Option<InputAction> {
+        self.bindings
+            .iter()
+            .find(|(_, keys)| keys.iter().any(|combo| combo.matches(key, modifiers)))
+            .map(|&(action, _)| action)
+    }
+
+    /// Every action with its keys joined for display, for the cheat sheet.
+    pub fn cheat_sheet(&self) -> Vec<(&'static str, String)> {
+        self.bindings
+            .iter()
+            .map(|(action, keys)| {
+                let keys = match keys.is_empty() {
+                    true => "unbound".to_string(),
+                    false => keys
+                        .iter()
+                        .map(KeyCombo::to_string)
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                };
+                (action.description(), keys)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn combo(s: &str) -> KeyCombo {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn combos_parse_and_display() {
+        let redo = combo("Ctrl+Shift+Z");
+        assert_eq!(redo.key, VirtualKeyCode::Z);
+        assert!(redo.ctrl && redo.shift && !redo.alt);
+        assert_eq!(redo.to_string(), "Ctrl+Shift+Z");
+        assert_eq!(combo("shift + control + z"), redo);
+        assert_eq!(combo("alt+f1").to_string(), "Alt+F1");
+        assert_eq!(combo("7").key, VirtualKeyCode::Key7);
+    }
+
+    #[test]
+    fn plus_is_a_key() {
+        assert_eq!(combo("Plus").key, VirtualKeyCode::Plus);
+        assert_eq!(combo("+").key, VirtualKeyCode::Plus);
+        let zoom = combo("Ctrl++");
+        assert_eq!((zoom.key, zoom.ctrl), (VirtualKeyCode::Plus, true));
+        assert_eq!(zoom.to_string(), "Ctrl+Plus");
+    }
+
+    #[test]
+    fn unknown_keys_and_modifiers_are_rejected() {
+        assert!("Banana".parse::<KeyCombo>().is_err());
+        assert!("Ctrl+".parse::<KeyCombo>().is_err());
+        assert!("Hyper+A".parse::<KeyCombo>().is_err());
+        assert!("".parse::<KeyCombo>().is_err());
+    }
+
+    #[test]
+    fn default_keys_round_trip() {
+        for action in InputAction::ALL {
+            for &key in action.default_keys() {
+                assert_eq!(combo(key).to_string(), key, "{action:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn modifiers_must_match_exactly() {
+        let keymap = Keymap::new(&BTreeMap::new());
+        let ctrl = ModifiersState::CTRL;
+        let clear = keymap.action(VirtualKeyCode::R, ModifiersState::empty());
+        assert_eq!(clear, Some(InputAction::Clear));
+        let reset = keymap.action(VirtualKeyCode::R, ctrl);
+        assert_eq!(reset, Some(InputAction::ResetToGenerationZero));
+        assert_eq!(keymap.action(VirtualKeyCode::R, ModifiersState::ALT), None);
+    }
+}
@@ -1,62 +1,176 @@
+mod animation;
+mod camera;
 mod cell;
+mod cli;
+mod config;
 mod gpu;
 mod gui;
+mod headless;
+mod history;
+mod keymap;
 mod math;
+mod minimap;
+mod pattern;
+mod rule;
+mod session;
 mod state;
+mod svg;
+mod theme;
+mod tools;
+mod universe;
 
+use clap::Parser;
+use config::Config;
 use egui_winit::winit;
 use state::State;
 use winit::event::{Event, WindowEvent};
 use winit::event_loop::ControlFlow;
 
-const WIN_WIDTH: u32 = 610;
-const WIN_HEIGHT: u32 = 610;
-
-fn setup_window() -> (winit::event_loop::EventLoop<()>, winit::window::Window) {
+fn setup_logger() {
     simple_logger::SimpleLogger::new()
         .with_level(log::LevelFilter::Info)
         .with_module_level("wgpu_core", log::LevelFilter::Warn)
         .with_module_level("wgpu_hal", log::LevelFilter::Warn)
         .init()
         .unwrap();
+}
 
+fn setup_window(
+    size: winit::dpi::PhysicalSize<u32>,
+    position: Option<winit::dpi::PhysicalPosition<i32>>,
+) -> (winit::event_loop::EventLoop<()>, winit::window::Window) {
     let event_loop = winit::event_loop::EventLoop::new();
-    let window = winit::window::WindowBuilder::new()
-        .with_title("Conway's Game of Life")
-        .with_inner_size(winit::dpi::PhysicalSize::new(WIN_WIDTH, WIN_HEIGHT))
-        .with_resizable(false)
-        .build(&event_loop)
-        .unwrap();
+    let mut builder = winit::window::WindowBuilder::new()
+        .with_title(state::WINDOW_TITLE)
+        .with_inner_size(size)
+        .with_resizable(true);
+    if let Some(position) = position {
+        builder = builder.with_position(position);
+    }
+    let window = builder.build(&event_loop).unwrap();
 
     log::info!("winit window initialized");
 
     (event_loop, window)
 }
 
+fn exit(state: &State, control_flow: &mut ControlFlow) {
+    if state.save_config {
+        state.config().save();
+    }
+    *control_flow = ControlFlow::Exit
+}
+
 fn handle_win_event(event: &WindowEvent, state: &mut State, control_flow: &mut ControlFlow) {
     match event {
-        WindowEvent::CloseRequested => *control_flow = ControlFlow::Exit,
-        WindowEvent::ScaleFactorChanged { new_inner_size, .. } => state.resize(**new_inner_size),
+        WindowEvent::CloseRequested => {
+            if state.request_quit() {
+                exit(state, control_flow)
+            }
+        }
+        WindowEvent::Resized(size) => state.resize(*size, state.window().scale_factor()),
+        WindowEvent::ScaleFactorChanged {
+            new_inner_size,
+            scale_factor,
+        } => state.resize(**new_inner_size, *scale_factor),
         _ => state.input(event),
     }
 }
 
 fn run(event_loop: winit::event_loop::EventLoop<()>, mut state: State) {
-    state.gosper_glider_gun();
-    state.blinkers();
-    state.pulsars();
-
     event_loop.run(move |event, _, control_flow| match event {
         Event::WindowEvent { ref event, .. } => handle_win_event(event, &mut state, control_flow),
-        Event::RedrawRequested(_) => state.update(),
+        Event::RedrawRequested(_) => {
+            state.update();
+            if state.quit_confirmed() {
+                exit(&state, control_flow)
+            }
+        }
         Event::MainEventsCleared => state.window().request_redraw(),
         _ => (),
     })
 }
 
 fn main() {
-    let (event_loop, window) = setup_window();
-    let state = state::init(window, &event_loop);
+    let cli = cli::Cli::parse();
+
+    if let Some(command) = cli.command {
+        let result = match command {
+            cli::Command::Run(args) => headless::run(args),
+            cli::Command::Animate(args) => headless::animate(args),
+            cli::Command::Frames(args) => headless::frames(args),
+            cli::Command::Svg(args) => headless::svg(args),
+        };
+        if let Err(e) = result {
+            eprintln!("error: {e}");
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    setup_logger();
+    // A config file that exists but can't be used is left alone rather than
+    // replaced by the defaults on exit.
+    let (config, save_config) = match Config::try_load() {
+        Ok(config) => (config, true),
+        Err(e) => {
+            log::warn!("ignoring {e}; settings won't be saved on exit");
+            (Config::default(), false)
+        }
+    };
+
+    let args = cli.gui;
+    let pattern = match args.pattern.as_deref().map(pattern::Pattern::load) {
+        Some(Ok(pattern)) => Some(pattern),
+        Some(Err(e)) => {
+            eprintln!("error: {e}");
+            std::process::exit(1);
+        }
+        None => None,
+    };
+
+    let universe = match &pattern {
+        Some(pattern) => args
+            .universe
+            .universe_with(pattern, Some(config.simulation.rule))
+            .unwrap_or_else(|e| {
+                eprintln!("error: {e}");
+                std::process::exit(1);
+            }),
+        None => args.universe.universe(Some(config.simulation.rule)),
+    };
+
+    let size = winit::dpi::PhysicalSize::new(
+        args.window_width.unwrap_or(config.window.width),
+        args.window_height.unwrap_or(config.window.height),
+    );
+    let position = match (config.window.x, config.window.y) {
+        (Some(x), Some(y)) => Some(winit::dpi::PhysicalPosition::new(x, y)),
+        _ => None,
+    };
+    let (event_loop, window) = setup_window(size, position);
+    let mut state = match state::init(window, &event_loop, universe, &config, &args) {
+        Ok(state) => state,
+        Err(e) => {
+            eprintln!("error: {e}");
+            std::process::exit(1);
+        }
+    };
+    state.save_config = save_config;
+
+    if let Some(path) = &args.session {
+        if let Err(e) = state.load_session(path) {
+            eprintln!("error: {e}");
+            std::process::exit(1);
+        }
+    } else if let Some(pattern) = pattern {
+        state.pattern_name = pattern.name;
+        state.fit_pattern();
+    } else {
+        state.gosper_glider_gun();
+        state.blinkers();
+        state.pulsars();
+    }
 
     run(event_loop, state);
 }
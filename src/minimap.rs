@@ -0,0 +1,57 @@
+use crate::theme::Theme;
+use crate::universe::Universe;
+
+/// Longest side of the minimap image in pixels.
+pub const MAX_SIZE: u32 = 200;
+
+/// Downscaled overview of the whole board. Each pixel covers a `scale` x
+/// `scale` block of cells and holds the fraction of them that are alive.
+pub struct Minimap {
+    pub width: u32,
+    pub height: u32,
+    pub scale: u32,
+    density: Vec<f32>,
+}
+
+impl Minimap {
+    pub fn new(universe: &Universe) -> Self {
+        let scale = universe.width().max(universe.height()).div_ceil(MAX_SIZE);
+        let width = universe.width().div_ceil(scale);
+        let height = universe.height().div_ceil(scale);
+
+        let mut counts = vec![0u32; (width * height) as usize];
+        for cell in universe.cells.iter().filter(|c| c.state.is_alive()) {
+            counts[(cell.x / scale + cell.y / scale * width) as usize] += 1;
+        }
+        let block = (scale * scale) as f32;
+        Self {
+            width,
+            height,
+            scale,
+            density: counts.into_iter().map(|n| n as f32 / block).collect(),
+        }
+    }
+
+    /// The density as an image shaded from the theme's dead color to its
+    /// alive color. Any live cell at all shows up, however sparse the block.
+    pub fn image(&self, theme: &Theme) -> egui::ColorImage {
+        let [dead, alive] = [theme.dead.0, theme.alive.0];
+        let pixels = self
+            .density
+            .iter()
+            .map(|&density| {
+                let t = match density {
+                    d if d > 0.0 => 0.3 + 0.7 * d.sqrt(),
+                    _ => 0.0,
+                };
+                let channel =
+                    |i: usize| (dead[i] as f32 + (alive[i] as f32 - dead[i] as f32) * t) as u8;
+                egui::Color32::from_rgb(channel(0), channel(1), channel(2))
+            })
+            .collect();
+        egui::ColorImage {
+            size: [self.width as usize, self.height as usize],
+            pixels,
+        }
+    }
+}
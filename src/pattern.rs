@@ -0,0 +1,466 @@
+use std::fmt::{self, Write as _};
+use std::path::Path;
+
+use crate::cell::CellState;
+use crate::rule::Rule;
+use crate::universe::Universe;
+
+const RLE_LINE_WIDTH: usize = 70;
+
+#[derive(Debug)]
+pub enum PatternError {
+    Io(std::io::Error),
+    Parse(String),
+    /// A `pattern` sized pattern placed on a smaller `board`.
+    DoesNotFit {
+        pattern: (u32, u32),
+        board: (u32, u32),
+    },
+}
+
+impl fmt::Display for PatternError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PatternError::Io(e) => write!(f, "{e}"),
+            PatternError::Parse(e) => write!(f, "invalid pattern: {e}"),
+            PatternError::DoesNotFit { pattern, board } => write!(
+                f,
+                "the {}x{} pattern doesn't fit the {}x{} board",
+                pattern.0, pattern.1, board.0, board.1
+            ),
+        }
+    }
+}
+
+impl From<std::io::Error> for PatternError {
+    fn from(e: std::io::Error) -> Self {
+        PatternError::Io(e)
+    }
+}
+
+/// A set of live cells relative to the pattern's top-left corner.
+#[derive(Clone, Debug, Default)]
+pub struct Pattern {
+    pub name: Option<String>,
+    pub comments: Vec<String>,
+    pub rule: Option<Rule>,
+    pub width: u32,
+    pub height: u32,
+    pub cells: Vec<(u32, u32)>,
+    /// Cells in the dying states of a Generations rule, as `(x, y, state)`.
+    pub dying: Vec<(u32, u32, u8)>,
+}
+
+/// Dying cells of `universe` that pass `keep`, with their positions mapped
+/// by `offset`.
+fn dying_cells(
+    universe: &Universe,
+    keep: impl Fn(u32, u32) -> bool,
+    offset: (u32, u32),
+) -> Vec<(u32, u32, u8)> {
+    universe
+        .cells
+        .iter()
+        .filter_map(|c| match c.state {
+            CellState::Dying(n) if keep(c.x, c.y) => Some((c.x - offset.0, c.y - offset.1, n)),
+            _ => None,
+        })
+        .collect()
+}
+
+/// RLE tag of a state in multi-state patterns: `.` for dead, `A` to `X` for
+/// states 1 to 24, then `pA` to `pX`, `qA`, ... for the rest.
+fn state_tag(state: u8) -> String {
+    match state {
+        0 => ".".to_string(),
+        1..=24 => char::from(b'A' + state - 1).to_string(),
+        _ => {
+            let prefix = char::from(b'p' + (state - 25) / 24);
+            let letter = char::from(b'A' + (state - 1) % 24);
+            format!("{prefix}{letter}")
+        }
+    }
+}
+
+impl Pattern {
+    pub fn load(path: &Path) -> Result<Self, PatternError> {
+        let text = std::fs::read_to_string(path)?;
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("cells") | Some("txt") => Self::parse_plaintext(&text),
+            _ => Self::parse_rle(&text),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), PatternError> {
+        let text = match path.extension().and_then(|e| e.to_str()) {
+            Some("cells") | Some("txt") => self.to_plaintext(),
+            _ => self.to_rle(),
+        };
+        std::fs::write(path, text)?;
+        Ok(())
+    }
+
+    /// Live cells of `universe`, cropped to their bounding box.
+    pub fn from_universe(universe: &Universe) -> Self {
+        let (min_x, min_y, width, height) = universe.bounding_box().unwrap_or((0, 0, 0, 0));
+        let cells = universe
+            .cells
+            .iter()
+            .filter(|c| c.state.is_alive())
+            .map(|c| (c.x - min_x, c.y - min_y))
+            .collect();
+
+        Self {
+            rule: Some(universe.rule),
+            width,
+            height,
+            cells,
+            dying: dying_cells(universe, |_, _| true, (min_x, min_y)),
+            ..Default::default()
+        }
+    }
+
+    /// Live cells of `universe` inside the `(x, y, width, height)` rectangle,
+    /// relative to its top-left corner.
+    pub fn from_region(universe: &Universe, rect: (u32, u32, u32, u32)) -> Self {
+        let (x, y, width, height) = rect;
+        let cells = universe
+            .cells
+            .iter()
+            .filter(|c| c.state.is_alive())
+            .filter(|c| c.x >= x && c.y >= y && c.x - x < width && c.y - y < height)
+            .map(|c| (c.x - x, c.y - y))
+            .collect();
+        let inside = |cx: u32, cy: u32| cx >= x && cy >= y && cx - x < width && cy - y < height;
+
+        Self {
+            rule: Some(universe.rule),
+            width,
+            height,
+            cells,
+            dying: dying_cells(universe, inside, (x, y)),
+            ..Default::default()
+        }
+    }
+
+    /// Live cells of `universe` at their absolute positions, so placing the
+    /// result at `(0, 0)` reproduces the board.
+    pub fn snapshot(universe: &Universe) -> Self {
+        let cells = universe
+            .cells
+            .iter()
+            .filter(|c| c.state.is_alive())
+            .map(|c| (c.x, c.y))
+            .collect();
+
+        Self {
+            rule: Some(universe.rule),
+            cells,
+            dying: dying_cells(universe, |_, _| true, (0, 0)),
+            ..Default::default()
+        }
+        .finish()
+    }
+
+    /// Stamps the pattern into `universe` with its top-left corner at `(x, y)`,
+    /// dropping any cell that falls outside the board.
+    pub fn place(&self, universe: &mut Universe, x: u32, y: u32) {
+        let alive = self.cells.iter().map(|&(cx, cy)| (cx, cy, 1));
+        for (cx, cy, state) in alive.chain(self.dying.iter().copied()) {
+            let (cx, cy) = (cx + x, cy + y);
+            if cx < universe.width() && cy < universe.height() {
+                universe.set(cx, cy, CellState::from_index(state));
+            }
+        }
+    }
+
+    pub fn place_centered(&self, universe: &mut Universe) {
+        let x = universe.width().saturating_sub(self.width) / 2;
+        let y = universe.height().saturating_sub(self.height) / 2;
+        self.place(universe, x, y);
+    }
+
+    fn finish(mut self) -> Self {
+        let positions = || {
+            let dying = self.dying.iter().map(|&(x, y, _)| (x, y));
+            self.cells.iter().copied().chain(dying)
+        };
+        self.width = positions().map(|c| c.0 + 1).max().unwrap_or(0);
+        self.height = positions().map(|c| c.1 + 1).max().unwrap_or(0);
+        self
+    }
+
+    pub fn parse_rle(text: &str) -> Result<Self, PatternError> {
+        let mut pattern = Self::default();
+        // Size from the header; runs may not go past it.
+        let (mut width, mut height) = (u32::MAX, u32::MAX);
+        let mut lines = text.lines().map(str::trim).filter(|l| !l.is_empty());
+
+        for line in lines.by_ref() {
+            if let Some(comment) = line.strip_prefix('#') {
+                match comment.split_at(comment.len().min(1)) {
+                    ("N", name) => pattern.name = Some(name.trim().to_owned()),
+                    ("C" | "c", text) => pattern.comments.push(text.trim().to_owned()),
+                    _ => (),
+                }
+                continue;
+            }
+
+            // The rule comes last and runs to the end of the line, since
+            // Larger than Life rulestrings contain commas themselves.
+            let (fields, rule) = match line.split_once("rule") {
+                Some((fields, rule)) => (fields, Some(rule)),
+                None => (line, None),
+            };
+            for field in fields.split(',').filter(|f| !f.trim().is_empty()) {
+                let bad_field = || PatternError::Parse(format!("bad header field '{field}'"));
+                let (key, value) = field.split_once('=').ok_or_else(bad_field)?;
+                let size = || value.trim().parse::<u32>().map_err(|_| bad_field());
+                match key.trim() {
+                    "x" => width = size()?,
+                    "y" => height = size()?,
+                    _ => (),
+                }
+            }
+            if let Some(rule) = rule {
+                let Some(value) = rule.trim_start().strip_prefix('=') else {
+                    return Err(PatternError::Parse(format!(
+                        "bad header field 'rule{rule}'"
+                    )));
+                };
+                pattern.rule = Some(value.parse().map_err(PatternError::Parse)?);
+            }
+            break;
+        }
+
+        let (mut x, mut y) = (0u32, 0u32);
+        let mut run = 0u32;
+        let too_long = || PatternError::Parse("run goes past the pattern size".to_owned());
+        // States the header rule allows; without one, any state a tag can name.
+        let max_state = pattern.rule.map_or(u8::MAX as u32, |r| r.states - 1);
+        'body: for line in lines {
+            let mut chars = line.chars().peekable();
+            while let Some(c) = chars.next() {
+                let state = match c {
+                    '0'..='9' => {
+                        run = run
+                            .checked_mul(10)
+                            .and_then(|run| run.checked_add(c.to_digit(10).unwrap()))
+                            .ok_or_else(too_long)?;
+                        continue;
+                    }
+                    // Multi-state tags above 24 start with a `p` to `y` prefix,
+                    // anything else lowercase is a live cell.
+                    'p'..='y' if chars.peek().is_some_and(|l| ('A'..='X').contains(l)) => {
+                        let letter = chars.next().unwrap();
+                        (c as u32 - 'p' as u32 + 1) * 24 + (letter as u32 - 'A' as u32 + 1)
+                    }
+                    c if c.is_whitespace() => continue,
+                    'b' | '.' => 0,
+                    '$' => {
+                        y = y
+                            .checked_add(run.max(1))
+                            .filter(|&y| y <= height)
+                            .ok_or_else(too_long)?;
+                        x = 0;
+                        run = 0;
+                        continue;
+                    }
+                    '!' => break 'body,
+                    'A'..='X' => c as u32 - 'A' as u32 + 1,
+                    c if c.is_ascii_alphabetic() => 1,
+                    c => return Err(PatternError::Parse(format!("unexpected '{c}' in RLE"))),
+                };
+                if state > max_state {
+                    return Err(PatternError::Parse(match pattern.rule {
+                        Some(rule) => format!("state {state} is out of range for rule {rule}"),
+                        None => format!("state {state} is out of range"),
+                    }));
+                }
+                let end = x
+                    .checked_add(run.max(1))
+                    .filter(|&end| end <= width && y < height)
+                    .ok_or_else(too_long)?;
+                match state as u8 {
+                    0 => (),
+                    1 => pattern.cells.extend((x..end).map(|x| (x, y))),
+                    n => pattern.dying.extend((x..end).map(|x| (x, y, n))),
+                }
+                x = end;
+                run = 0;
+            }
+        }
+
+        Ok(pattern.finish())
+    }
+
+    pub fn parse_plaintext(text: &str) -> Result<Self, PatternError> {
+        let mut pattern = Self::default();
+        let mut y = 0;
+
+        for line in text.lines() {
+            if let Some(comment) = line.strip_prefix('!') {
+                match comment.strip_prefix("Name:") {
+                    Some(name) => pattern.name = Some(name.trim().to_owned()),
+                    None => pattern.comments.push(comment.trim().to_owned()),
+                }
+                continue;
+            }
+            for (x, c) in line.trim_end().chars().enumerate() {
+                match c {
+                    '.' => (),
+                    'O' | 'o' | '*' => pattern.cells.push((x as u32, y)),
+                    c => {
+                        return Err(PatternError::Parse(format!(
+                            "unexpected '{c}' in plaintext"
+                        )))
+                    }
+                }
+            }
+            y += 1;
+        }
+
+        Ok(pattern.finish())
+    }
+
+    /// State indices of every cell, row by row.
+    pub fn rows(&self) -> Vec<Vec<u8>> {
+        let mut rows = vec![vec![0; self.width as usize]; self.height as usize];
+        for &(x, y) in self.cells.iter() {
+            rows[y as usize][x as usize] = 1;
+        }
+        for &(x, y, state) in self.dying.iter() {
+            rows[y as usize][x as usize] = state;
+        }
+        rows
+    }
+
+    pub fn to_rle(&self) -> String {
+        let mut out = String::new();
+        if let Some(name) = &self.name {
+            writeln!(out, "#N {name}").unwrap();
+        }
+        for comment in self.comments.iter() {
+            writeln!(out, "#C {comment}").unwrap();
+        }
+        write!(out, "x = {}, y = {}", self.width, self.height).unwrap();
+        if let Some(rule) = &self.rule {
+            write!(out, ", rule = {rule}").unwrap();
+        }
+        out.push('\n');
+
+        // Generations rules need a tag per state, written the way Golly does.
+        let multi_state = self.rule.is_some_and(|r| r.is_generations()) || !self.dying.is_empty();
+        let tag = |state: u8| match (multi_state, state) {
+            (true, state) => state_tag(state),
+            (false, 0) => "b".to_string(),
+            (false, _) => "o".to_string(),
+        };
+
+        // Runs of `(count, tag)`; trailing dead cells and empty rows are folded
+        // into the following `$` as allowed by the format.
+        let mut runs: Vec<(u32, String)> = vec![];
+        let push = |runs: &mut Vec<(u32, String)>, count: u32, tag: String| match runs.last_mut() {
+            Some((n, t)) if *t == tag => *n += count,
+            _ => runs.push((count, tag)),
+        };
+        for (y, row) in self.rows().iter().enumerate() {
+            if y > 0 {
+                push(&mut runs, 1, "$".to_string());
+            }
+            let len = row
+                .iter()
+                .rposition(|&state| state != 0)
+                .map_or(0, |i| i + 1);
+            for &state in row[..len].iter() {
+                push(&mut runs, 1, tag(state));
+            }
+        }
+        while runs.last().is_some_and(|(_, tag)| tag == "$") {
+            runs.pop();
+        }
+        runs.push((1, "!".to_string()));
+
+        let mut line_len = 0;
+        for (count, tag) in runs {
+            let item = match count {
+                1 => tag.to_string(),
+                n => format!("{n}{tag}"),
+            };
+            if line_len + item.len() > RLE_LINE_WIDTH {
+                out.push('\n');
+                line_len = 0;
+            }
+            line_len += item.len();
+            out.push_str(&item);
+        }
+        out.push('\n');
+        out
+    }
+
+    pub fn to_plaintext(&self) -> String {
+        let mut out = String::new();
+        if let Some(name) = &self.name {
+            writeln!(out, "!Name: {name}").unwrap();
+        }
+        for comment in self.comments.iter() {
+            writeln!(out, "!{comment}").unwrap();
+        }
+        for row in self.rows() {
+            // Plaintext has no dying states, they are written as dead.
+            let line: String = row
+                .iter()
+                .map(|&s| if s == 1 { 'O' } else { '.' })
+                .collect();
+            writeln!(out, "{}", line.trim_end_matches('.')).unwrap();
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn multi_state_tags() {
+        let rle = "x = 4, y = 1, rule = B2/S/C256\n.ApAyO!\n";
+        let pattern = Pattern::parse_rle(rle).unwrap();
+        assert_eq!(pattern.cells, [(1, 0)]);
+        assert_eq!(pattern.dying, [(2, 0, 25), (3, 0, 255)]);
+        assert_eq!(pattern.to_rle(), rle);
+    }
+
+    #[test]
+    fn snapshot_keeps_prefixed_states() {
+        let mut universe = Universe::new(4, 4, "B2/S/C30".parse().unwrap());
+        universe.set(1, 1, CellState::Dying(25));
+        universe.set(2, 3, CellState::Alive);
+        let pattern = Pattern::parse_rle(&Pattern::snapshot(&universe).to_rle()).unwrap();
+        assert_eq!(pattern.cells, [(2, 3)]);
+        assert_eq!(pattern.dying, [(1, 1, 25)]);
+    }
+
+    #[test]
+    fn lowercase_letters_are_live_cells() {
+        let pattern = Pattern::parse_rle("x = 3, y = 1\npqo!").unwrap();
+        assert_eq!(pattern.cells, [(0, 0), (1, 0), (2, 0)]);
+    }
+
+    #[test]
+    fn states_beyond_the_rule_are_rejected() {
+        assert!(Pattern::parse_rle("x = 2, y = 1, rule = B3/S23\nAB!").is_err());
+        assert!(Pattern::parse_rle("x = 1, y = 1, rule = B2/S/C3\npA!").is_err());
+        assert!(Pattern::parse_rle("x = 1, y = 1, rule = B3/S23\nA!").is_ok());
+    }
+
+    #[test]
+    fn runs_are_bounded() {
+        assert!(Pattern::parse_rle("x = 3, y = 2\n99999999999o!").is_err());
+        assert!(Pattern::parse_rle("x = 3, y = 2\n4o!").is_err());
+        assert!(Pattern::parse_rle("x = 3, y = 2\no3$o!").is_err());
+        let pattern = Pattern::parse_rle("x = 3, y = 2\n3o$2bo!").unwrap();
+        assert_eq!(pattern.cells, [(0, 0), (1, 0), (2, 0), (2, 1)]);
+        assert!(Pattern::parse_rle("x = three, y = 2\no!").is_err());
+    }
+}
@@ -0,0 +1,683 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::cell::CellState;
+
+/// Most states a Generations rule can have, so every state fits in the byte
+/// per cell the shaders keep.
+pub const MAX_STATES: u32 = 256;
+
+/// Largest neighborhood range of Larger than Life rules.
+pub const MAX_RANGE: u32 = 10;
+
+/// Words in a [`Counts`] set, enough for every count up to a full range
+/// [`MAX_RANGE`] Moore neighborhood and a multiple of 4 so the set can be
+/// passed to shaders as an array of `vec4`.
+pub const COUNT_WORDS: usize = 16;
+
+/// Cells whose live neighbors are counted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Neighborhood {
+    /// The square of cells at most `range` away on both axes
+    Moore,
+    /// The diamond of cells at most `range` steps away
+    VonNeumann,
+    /// The disc of cells within `range + 1/2` cells
+    Circular,
+}
+
+impl Neighborhood {
+    /// How far the neighborhood reaches left and right on the row `dy` rows
+    /// from the center, for a neighborhood of the given range.
+    pub fn half_width(self, range: u32, dy: u32) -> u32 {
+        match self {
+            Neighborhood::Moore => range,
+            Neighborhood::VonNeumann => range - dy,
+            Neighborhood::Circular => {
+                // dx² + dy² <= r² + r, the same disc LifeViewer and Golly use.
+                let limit = range * range + range - dy * dy;
+                let mut width = range - dy.min(range);
+                while (width + 1) * (width + 1) <= limit {
+                    width += 1;
+                }
+                width
+            }
+        }
+    }
+
+    fn letter(self) -> char {
+        match self {
+            Neighborhood::Moore => 'M',
+            Neighborhood::VonNeumann => 'N',
+            Neighborhood::Circular => 'C',
+        }
+    }
+}
+
+/// A set of neighbor counts, bit `n` set for `n` live neighbors.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Counts(pub [u32; COUNT_WORDS]);
+
+impl Counts {
+    /// Largest count the set can hold.
+    pub const MAX: usize = COUNT_WORDS * 32 - 1;
+
+    pub fn contains(&self, n: usize) -> bool {
+        n <= Self::MAX && self.0[n / 32] >> (n % 32) & 1 == 1
+    }
+
+    pub fn insert(&mut self, n: usize) {
+        self.0[n / 32] |= 1 << (n % 32);
+    }
+
+    fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..=Self::MAX).filter(|&n| self.contains(n))
+    }
+
+    /// The set as one `min..=max` range, if it is one.
+    fn as_range(&self) -> Option<(usize, usize)> {
+        let min = self.iter().next()?;
+        let max = self.iter().last()?;
+        (self.iter().count() == max - min + 1).then_some((min, max))
+    }
+}
+
+/// The 8 nearest neighbors in reading order. An arrangement of live
+/// neighbors has bit 7 set for the first one, down to bit 0 for the last.
+pub const NEIGHBORS: [(i32, i32); 8] = [
+    (-1, -1),
+    (0, -1),
+    (1, -1),
+    (-1, 0),
+    (1, 0),
+    (-1, 1),
+    (0, 1),
+    (1, 1),
+];
+
+/// Hensel notation letters for each neighbor count up to 4, with one
+/// arrangement of each; the others are its rotations and reflections.
+/// Counts 5 to 8 use the same letters for the complements of 3 to 0.
+const HENSEL: [&[(char, u8)]; 5] = [
+    &[],
+    &[('c', 0x01), ('e', 0x02)],
+    &[
+        ('c', 0x05),
+        ('e', 0x0a),
+        ('a', 0x03),
+        ('i', 0x18),
+        ('k', 0x11),
+        ('n', 0x24),
+    ],
+    &[
+        ('c', 0x25),
+        ('e', 0x1a),
+        ('a', 0x0b),
+        ('i', 0x07),
+        ('k', 0x32),
+        ('n', 0x0d),
+        ('j', 0x0e),
+        ('q', 0x26),
+        ('r', 0x19),
+        ('y', 0x31),
+    ],
+    &[
+        ('c', 0xa5),
+        ('e', 0x5a),
+        ('a', 0x0f),
+        ('i', 0x1d),
+        ('k', 0x33),
+        ('n', 0x27),
+        ('j', 0x3a),
+        ('q', 0x36),
+        ('r', 0x1b),
+        ('t', 0x35),
+        ('w', 0x39),
+        ('y', 0x2e),
+        ('z', 0x3c),
+    ],
+];
+
+/// The letters of `count` live neighbors with their representative
+/// arrangements.
+fn hensel_letters(count: usize) -> impl Iterator<Item = (char, u8)> {
+    let (letters, complement) = match count {
+        0..=4 => (HENSEL[count], false),
+        _ => (HENSEL[8 - count], true),
+    };
+    letters
+        .iter()
+        .map(move |&(letter, arrangement)| match complement {
+            true => (letter, !arrangement),
+            false => (letter, arrangement),
+        })
+}
+
+/// `arrangement` rotated and reflected in all 8 ways.
+fn symmetries(arrangement: u8) -> impl Iterator<Item = u8> {
+    (0..8).map(move |symmetry| {
+        let mut out = 0;
+        for (i, &(dx, dy)) in NEIGHBORS.iter().enumerate() {
+            if arrangement >> (7 - i) & 1 == 0 {
+                continue;
+            }
+            let (mut x, mut y) = (dx, dy);
+            for _ in 0..symmetry % 4 {
+                (x, y) = (-y, x);
+            }
+            if symmetry >= 4 {
+                x = -x;
+            }
+            let j = NEIGHBORS.iter().position(|&n| n == (x, y)).unwrap();
+            out |= 1 << (7 - j);
+        }
+        out
+    })
+}
+
+/// A set of arrangements of live nearest neighbors, see [`NEIGHBORS`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Arrangements(pub [u32; 8]);
+
+impl Arrangements {
+    pub fn contains(&self, arrangement: u8) -> bool {
+        self.0[arrangement as usize / 32] >> (arrangement % 32) & 1 == 1
+    }
+
+    pub fn insert(&mut self, arrangement: u8) {
+        self.0[arrangement as usize / 32] |= 1 << (arrangement % 32);
+    }
+
+    /// Every arrangement with a neighbor count in `counts`.
+    fn from_counts(counts: &Counts) -> Self {
+        let mut arrangements = Self::default();
+        for arrangement in 0..=255u8 {
+            if counts.contains(arrangement.count_ones() as usize) {
+                arrangements.insert(arrangement);
+            }
+        }
+        arrangements
+    }
+
+    /// Hensel notation for the arrangements, e.g. `2-a3`. Counts missing a
+    /// few letters list the ones left out after a `-`.
+    fn hensel(&self) -> String {
+        let mut s = String::new();
+        for count in 0..=8 {
+            let letters: Vec<(char, bool)> = hensel_letters(count)
+                .map(|(letter, arrangement)| (letter, self.contains(arrangement)))
+                .collect();
+            let included = letters.iter().filter(|(_, on)| *on).count();
+            if letters.is_empty() {
+                if self.contains(if count == 0 { 0 } else { 0xff }) {
+                    s.push(char::from(b'0' + count as u8));
+                }
+                continue;
+            }
+            if included == 0 {
+                continue;
+            }
+            s.push(char::from(b'0' + count as u8));
+            if included == letters.len() {
+                continue;
+            }
+            let exclude = letters.len() - included < included;
+            if exclude {
+                s.push('-');
+            }
+            s.extend(
+                letters
+                    .iter()
+                    .filter(|(_, on)| *on != exclude)
+                    .map(|(letter, _)| letter),
+            );
+        }
+        s
+    }
+}
+
+/// Which arrangements of live nearest neighbors give birth and survival in
+/// an isotropic non-totalistic rule.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Isotropic {
+    pub birth: Arrangements,
+    pub survival: Arrangements,
+}
+
+/// Outer totalistic rule: B/S notation for the 8 nearest neighbors, e.g.
+/// `B3/S23` for Conway's Life, or `R5,C0,M1,S34..58,B34..45,NM` for a Larger
+/// than Life rule. Either can be a Generations rule like `B2/S/C3` (Brian's
+/// Brain). Nearest neighbor rules can also use Hensel notation, like
+/// `B2-a/S12`, to depend on how the live neighbors are arranged.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Rule {
+    pub birth: Counts,
+    pub survival: Counts,
+    /// For isotropic non-totalistic rules, the arrangements that replace
+    /// `birth` and `survival`.
+    pub isotropic: Option<Isotropic>,
+    /// Number of cell states, dead and alive included. Above 2, live cells
+    /// that don't survive go through the extra states before dying.
+    pub states: u32,
+    /// How far away neighbors are counted, 1 for the usual 8 neighbors.
+    pub range: u32,
+    pub neighborhood: Neighborhood,
+    /// The cell itself counts as its own neighbor.
+    pub middle: bool,
+}
+
+impl Rule {
+    pub fn conway() -> Self {
+        "B3/S23".parse().unwrap()
+    }
+
+    /// Whether cells go through dying states, i.e. this is a Generations rule.
+    pub fn is_generations(&self) -> bool {
+        self.states > 2
+    }
+
+    /// Whether only the 8 nearest neighbors count, as in B/S rules.
+    pub fn is_nearest(&self) -> bool {
+        self.range == 1 && self.neighborhood == Neighborhood::Moore && !self.middle
+    }
+
+    /// Number of cells counted as neighbors.
+    pub fn neighborhood_size(&self) -> usize {
+        let rows = (1..=self.range)
+            .map(|dy| 2 * (2 * self.neighborhood.half_width(self.range, dy) + 1))
+            .sum::<u32>();
+        let middle_row = 2 * self.range + 1;
+        (rows + middle_row) as usize - !self.middle as usize
+    }
+
+    /// Birth and survival arrangements for a nearest neighbor rule, taken
+    /// from the counts unless the rule is isotropic.
+    pub fn arrangements(&self) -> Isotropic {
+        self.isotropic.unwrap_or_else(|| Isotropic {
+            birth: Arrangements::from_counts(&self.birth),
+            survival: Arrangements::from_counts(&self.survival),
+        })
+    }
+
+    /// The state after `state`, given its live neighbor count, or for
+    /// isotropic rules the arrangement of its live neighbors.
+    pub fn next_state(&self, state: CellState, neighbors: usize) -> CellState {
+        let (born, survives) = match &self.isotropic {
+            Some(table) => (
+                table.birth.contains(neighbors as u8),
+                table.survival.contains(neighbors as u8),
+            ),
+            None => (
+                self.birth.contains(neighbors),
+                self.survival.contains(neighbors),
+            ),
+        };
+        match state {
+            CellState::Dead if born => CellState::Alive,
+            CellState::Dead => CellState::Dead,
+            CellState::Alive if survives => CellState::Alive,
+            CellState::Alive if self.is_generations() => CellState::Dying(2),
+            CellState::Alive => CellState::Dead,
+            CellState::Dying(n) if (n as u32) + 1 < self.states => CellState::Dying(n + 1),
+            CellState::Dying(_) => CellState::Dead,
+        }
+    }
+
+    // `R5,C0,M1,S34..58,B34..45,NM`, in any order. C is the state count, with
+    // 0 meaning 2, M whether the middle cell counts and N the neighborhood.
+    fn parse_larger_than_life(s: &str) -> Result<Self, String> {
+        let invalid = |reason: String| format!("invalid rule '{s}': {reason}");
+        let mut rule = Rule {
+            birth: Counts::default(),
+            survival: Counts::default(),
+            isotropic: None,
+            states: 2,
+            range: 0,
+            neighborhood: Neighborhood::Moore,
+            middle: false,
+        };
+        let (mut birth, mut survival) = (None, None);
+        for field in s.split(',').map(str::trim) {
+            let (key, value) = field.split_at(field.len().min(1));
+            match key.to_ascii_uppercase().as_str() {
+                "R" => {
+                    rule.range = value
+                        .parse()
+                        .ok()
+                        .filter(|r| (1..=MAX_RANGE).contains(r))
+                        .ok_or_else(|| invalid(format!("range must be 1 to {MAX_RANGE}")))?
+                }
+                "C" => {
+                    rule.states = match value.parse() {
+                        Ok(0) => 2,
+                        Ok(n) if (2..=MAX_STATES).contains(&n) => n,
+                        _ => return Err(invalid(format!("state count must be 2 to {MAX_STATES}"))),
+                    }
+                }
+                "M" => {
+                    rule.middle = match value {
+                        "0" => false,
+                        "1" => true,
+                        _ => return Err(invalid("M must be 0 or 1".to_string())),
+                    }
+                }
+                "S" => survival = Some(parse_count_range(value).map_err(invalid)?),
+                "B" => birth = Some(parse_count_range(value).map_err(invalid)?),
+                "N" => {
+                    rule.neighborhood = match value.to_ascii_uppercase().as_str() {
+                        "M" => Neighborhood::Moore,
+                        "N" => Neighborhood::VonNeumann,
+                        "C" => Neighborhood::Circular,
+                        _ => return Err(invalid(format!("unknown neighborhood '{value}'"))),
+                    }
+                }
+                _ => return Err(invalid(format!("unknown field '{field}'"))),
+            }
+        }
+        if rule.range == 0 {
+            return Err(invalid("missing range".to_string()));
+        }
+
+        let size = rule.neighborhood_size();
+        for (counts, range, name) in [
+            (&mut rule.birth, birth, 'B'),
+            (&mut rule.survival, survival, 'S'),
+        ] {
+            let Some(range) = range else {
+                return Err(invalid(format!("missing {name}")));
+            };
+            if let Some((_, max)) = range {
+                if max > size {
+                    return Err(invalid(format!(
+                        "{name} goes up to {max}, but the neighborhood only has {size} cells"
+                    )));
+                }
+            }
+            for n in range.into_iter().flat_map(|(min, max)| min..=max) {
+                counts.insert(n);
+            }
+        }
+        Ok(rule)
+    }
+}
+
+impl std::default::Default for Rule {
+    fn default() -> Self {
+        Self::conway()
+    }
+}
+
+/// Parses neighbor counts, each optionally followed by Hensel letters that
+/// keep only some of its arrangements (`2ae`), or a `-` and the letters to
+/// leave out (`3-jr`). The arrangements are only returned when letters were
+/// used.
+fn parse_counts(s: &str) -> Result<(Counts, Option<Arrangements>), String> {
+    let mut counts = Counts::default();
+    let mut arrangements = Arrangements::default();
+    let mut letters_used = false;
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        let count = match c.to_digit(10) {
+            Some(n) if n <= 8 => n as usize,
+            _ => return Err(format!("invalid neighbor count '{c}'")),
+        };
+        counts.insert(count);
+
+        let exclude = chars.next_if_eq(&'-').is_some();
+        let mut letters = Vec::new();
+        while let Some(letter) = chars.next_if(char::is_ascii_lowercase) {
+            if !hensel_letters(count).any(|(l, _)| l == letter) {
+                return Err(format!("no arrangement '{letter}' of {count} neighbors"));
+            }
+            letters.push(letter);
+        }
+        if exclude && letters.is_empty() {
+            return Err(format!("expected letters after '{count}-'"));
+        }
+        letters_used |= !letters.is_empty();
+
+        let mut representatives: Vec<u8> = hensel_letters(count)
+            .filter(|(l, _)| letters.is_empty() || letters.contains(l) != exclude)
+            .map(|(_, arrangement)| arrangement)
+            .collect();
+        if count == 0 || count == 8 {
+            representatives.push(if count == 0 { 0 } else { 0xff });
+        }
+        for arrangement in representatives.into_iter().flat_map(symmetries) {
+            arrangements.insert(arrangement);
+        }
+    }
+    Ok((counts, letters_used.then_some(arrangements)))
+}
+
+/// `MIN..MAX` or a single count, `None` when empty.
+fn parse_count_range(s: &str) -> Result<Option<(usize, usize)>, String> {
+    if s.is_empty() {
+        return Ok(None);
+    }
+    let (min, max) = s.split_once("..").unwrap_or((s, s));
+    match (min.parse(), max.parse()) {
+        (Ok(min), Ok(max)) if min <= max => Ok(Some((min, max))),
+        _ => Err(format!("invalid count range '{s}'")),
+    }
+}
+
+impl FromStr for Rule {
+    type Err = String;
+
+    // Accepts both `B3/S23` and the older `23/3` (survival/birth) notation,
+    // each optionally followed by a state count: `B2/S/C3`, `345/2/4`, as
+    // well as Larger than Life rules. Counts can carry Hensel letters.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.starts_with(['R', 'r']) {
+            return Self::parse_larger_than_life(s);
+        }
+        let (first, second) = s
+            .split_once('/')
+            .ok_or_else(|| format!("invalid rule '{s}': expected '/'"))?;
+        let (second, states) = match second.split_once('/') {
+            Some((second, states)) => {
+                let count = states.trim_start_matches(['C', 'c', 'G', 'g']);
+                match count.parse() {
+                    Ok(n) if (2..=MAX_STATES).contains(&n) => (second, n),
+                    _ => {
+                        return Err(format!(
+                            "invalid rule '{s}': state count must be 2 to {MAX_STATES}"
+                        ))
+                    }
+                }
+            }
+            None => (second, 2),
+        };
+
+        let (birth, survival) = match (first.chars().next(), second.chars().next()) {
+            (Some('B' | 'b'), _) => (&first[1..], second.trim_start_matches(['S', 's'])),
+            (Some('S' | 's'), _) => (second.trim_start_matches(['B', 'b']), &first[1..]),
+            _ => (second, first),
+        };
+
+        let (birth, birth_arrangements) =
+            parse_counts(birth).map_err(|e| format!("invalid rule '{s}': {e}"))?;
+        let (survival, survival_arrangements) =
+            parse_counts(survival).map_err(|e| format!("invalid rule '{s}': {e}"))?;
+        let isotropic = Isotropic {
+            birth: birth_arrangements.unwrap_or_else(|| Arrangements::from_counts(&birth)),
+            survival: survival_arrangements.unwrap_or_else(|| Arrangements::from_counts(&survival)),
+        };
+        // Letters naming every arrangement of a count, as in `B2cekain`,
+        // still make a totalistic rule.
+        let totalistic = isotropic.birth == Arrangements::from_counts(&birth)
+            && isotropic.survival == Arrangements::from_counts(&survival);
+
+        Ok(Self {
+            birth,
+            survival,
+            isotropic: (!totalistic).then_some(isotropic),
+            states,
+            range: 1,
+            neighborhood: Neighborhood::Moore,
+            middle: false,
+        })
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !self.is_nearest() {
+            // Larger than Life rules can only hold single count ranges, which
+            // is all their rulestrings can express.
+            let range = |counts: &Counts| match counts.as_range() {
+                Some((min, max)) => format!("{min}..{max}"),
+                None => String::new(),
+            };
+            let states = if self.is_generations() {
+                self.states
+            } else {
+                0
+            };
+            return write!(
+                f,
+                "R{},C{},M{},S{},B{},N{}",
+                self.range,
+                states,
+                self.middle as u8,
+                range(&self.survival),
+                range(&self.birth),
+                self.neighborhood.letter()
+            );
+        }
+
+        if let Some(table) = &self.isotropic {
+            write!(f, "B{}/S{}", table.birth.hensel(), table.survival.hensel())?;
+        } else {
+            let digits = |counts: &Counts| -> String {
+                counts.iter().map(|n| char::from(b'0' + n as u8)).collect()
+            };
+            write!(f, "B{}/S{}", digits(&self.birth), digits(&self.survival))?;
+        }
+        if self.is_generations() {
+            write!(f, "/C{}", self.states)?;
+        }
+        Ok(())
+    }
+}
+
+impl serde::Serialize for Rule {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for Rule {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(s: &str, written: &str) {
+        let rule: Rule = s.parse().unwrap();
+        assert_eq!(rule.to_string(), written);
+        assert_eq!(written.parse::<Rule>(), Ok(rule));
+    }
+
+    #[test]
+    fn generations_rules_round_trip() {
+        round_trip("345/2/4", "B2/S345/C4");
+        round_trip("B2/S/C3", "B2/S/C3");
+        round_trip("B3/S23", "B3/S23");
+    }
+
+    #[test]
+    fn larger_than_life_rules_round_trip() {
+        round_trip("R5,C0,M1,S34..58,B34..45,NM", "R5,C0,M1,S34..58,B34..45,NM");
+        round_trip("R2,C3,M0,S2..3,B3..3,NN", "R2,C3,M0,S2..3,B3..3,NN");
+        let rule: Rule = "R5,C0,M1,S34..58,B34..45,NM".parse().unwrap();
+        assert_eq!(rule.neighborhood_size(), 121);
+    }
+
+    #[test]
+    fn hensel_rules_round_trip() {
+        round_trip("B3/S23-a4i", "B3/S23-a4i");
+        round_trip("B2cek3-j/S1e2-a3ny4/C5", "B2cek3-j/S1e2-a3ny4/C5");
+        round_trip("B3/S2-aeikn3", "B3/S2c3");
+        round_trip("B2cekain/S23", "B2/S23");
+    }
+
+    /// Arrangement of a 3x3 neighborhood drawn row by row, `o` for live
+    /// neighbors. The middle is ignored.
+    fn drawn(rows: [&str; 3]) -> u8 {
+        let cells: Vec<char> = rows.concat().chars().collect();
+        NEIGHBORS.iter().fold(0, |arrangement, &(dx, dy)| {
+            arrangement << 1 | (cells[(dx + 1 + (dy + 1) * 3) as usize] == 'o') as u8
+        })
+    }
+
+    /// Hensel classes, like `2a`, whose birth-only rule contains
+    /// `arrangement`.
+    fn classes(arrangement: u8) -> Vec<String> {
+        let count = arrangement.count_ones() as usize;
+        if matches!(count, 0 | 8) {
+            return vec![count.to_string()];
+        }
+        hensel_letters(count)
+            .map(|(letter, _)| format!("{count}{letter}"))
+            .filter(|class| {
+                let rule: Rule = format!("B{class}/S").parse().unwrap();
+                rule.arrangements().birth.contains(arrangement)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn hensel_classes_match_drawn_neighborhoods() {
+        let drawings = [
+            ("0", ["...", "...", "..."]),
+            ("1c", ["...", "...", "..o"]),
+            ("1e", ["...", "o..", "..."]),
+            ("2a", ["..o", "..o", "..."]),
+            ("2c", ["o..", "...", "o.."]),
+            ("2e", [".o.", "..o", "..."]),
+            ("2i", [".o.", "...", ".o."]),
+            ("2k", ["o..", "...", ".o."]),
+            ("2n", ["..o", "...", "o.."]),
+            ("3c", ["o.o", "...", "..o"]),
+            ("3i", ["o..", "o..", "o.."]),
+            ("4c", ["o.o", "...", "o.o"]),
+            ("4e", [".o.", "o.o", ".o."]),
+            ("6i", ["ooo", "...", "ooo"]),
+            ("7c", ["ooo", "o.o", "oo."]),
+            ("7e", ["ooo", "o.o", "o.o"]),
+            ("8", ["ooo", "o.o", "ooo"]),
+        ];
+        for (class, rows) in drawings {
+            assert_eq!(classes(drawn(rows)), [class], "{rows:?}");
+        }
+    }
+
+    #[test]
+    fn hensel_classes_split_every_arrangement_once() {
+        let mut seen = std::collections::HashSet::new();
+        for arrangement in 0..=255 {
+            let classes = classes(arrangement);
+            assert_eq!(classes.len(), 1, "{arrangement:08b}: {classes:?}");
+            seen.insert(classes[0].clone());
+        }
+        assert_eq!(seen.len(), 51);
+    }
+
+    #[test]
+    fn dying_cells_age_then_die() {
+        let rule: Rule = "345/2/4".parse().unwrap();
+        assert_eq!(rule.next_state(CellState::Alive, 4), CellState::Alive);
+        assert_eq!(rule.next_state(CellState::Alive, 2), CellState::Dying(2));
+        assert_eq!(rule.next_state(CellState::Dying(2), 2), CellState::Dying(3));
+        assert_eq!(rule.next_state(CellState::Dying(3), 2), CellState::Dead);
+        assert_eq!(rule.next_state(CellState::Dead, 2), CellState::Alive);
+    }
+}
@@ -0,0 +1,260 @@
+use std::fmt;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::camera::Camera;
+use crate::config::DisplayConfig;
+use crate::pattern::{Pattern, PatternError};
+use crate::rule::Rule;
+use crate::universe::{Topology, Universe};
+
+#[derive(Debug)]
+pub enum SessionError {
+    Io(std::io::Error),
+    Format(toml::de::Error),
+    Pattern(PatternError),
+    /// Per-cell data that doesn't cover the board.
+    Mismatch(&'static str),
+    EmptyBoard,
+    /// The board doesn't fit the GPU's buffer limits.
+    Gpu(String),
+}
+
+impl fmt::Display for SessionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SessionError::Io(e) => write!(f, "{e}"),
+            SessionError::Format(e) => write!(f, "invalid session file: {e}"),
+            SessionError::Pattern(e) => write!(f, "invalid session board: {e}"),
+            SessionError::Mismatch(what) => write!(f, "{what} don't match the board size"),
+            SessionError::EmptyBoard => write!(f, "the board must be at least 1x1"),
+            SessionError::Gpu(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl From<std::io::Error> for SessionError {
+    fn from(e: std::io::Error) -> Self {
+        SessionError::Io(e)
+    }
+}
+
+impl From<PatternError> for SessionError {
+    fn from(e: PatternError) -> Self {
+        SessionError::Pattern(e)
+    }
+}
+
+/// Per-cell values as `(count, value)` runs in `Universe::cells` order, so
+/// boards that are mostly alike stay small in the file.
+fn to_runs<T: Copy + PartialEq>(values: impl Iterator<Item = T>) -> Vec<(u32, T)> {
+    let mut runs: Vec<(u32, T)> = Vec::new();
+    for value in values {
+        match runs.last_mut() {
+            Some((count, last)) if *last == value => *count += 1,
+            _ => runs.push((1, value)),
+        }
+    }
+    runs
+}
+
+/// The values of `runs`, if there are exactly `len` of them.
+fn from_runs<T: Copy>(runs: &[(u32, T)], len: usize) -> Option<Vec<T>> {
+    let values: Vec<T> = runs
+        .iter()
+        .flat_map(|&(count, value)| std::iter::repeat_n(value, count as usize))
+        .take(len + 1)
+        .collect();
+    (values.len() == len).then_some(values)
+}
+
+/// Everything needed to reopen a board exactly where it was left. Boards are
+/// stored as RLE anchored at the top-left corner of the universe, so the
+/// files stay small and readable.
+#[derive(Serialize, Deserialize)]
+pub struct Session {
+    pub width: u32,
+    pub height: u32,
+    pub rule: Rule,
+    pub topology: Topology,
+    pub generation: u64,
+    pub speed: u32,
+    pub cells: String,
+    /// Generation each cell entered its state in, as runs (see `to_runs`),
+    /// -1 for cells that haven't been alive since the board was cleared.
+    /// Cell ages and trails need it; older files without it load with every
+    /// cell newborn.
+    #[serde(default)]
+    pub changed_at: Vec<(u32, i64)>,
+    /// The heat map, when it was on.
+    #[serde(default)]
+    pub heat: Option<SavedHeat>,
+    pub generation_zero: Option<String>,
+    pub display: DisplayConfig,
+    #[serde(default)]
+    pub camera: Camera,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SavedHeat {
+    pub window: u32,
+    /// Heat of each cell, as runs (see `to_runs`).
+    pub values: Vec<(u32, f32)>,
+}
+
+impl Session {
+    pub fn capture(
+        universe: &Universe,
+        generation_zero: Option<&Pattern>,
+        display: DisplayConfig,
+        camera: Camera,
+        speed: u32,
+    ) -> Self {
+        Self {
+            width: universe.width(),
+            height: universe.height(),
+            rule: universe.rule,
+            topology: universe.topology,
+            generation: universe.generation(),
+            speed,
+            cells: Pattern::snapshot(universe).to_rle(),
+            changed_at: to_runs(
+                (universe.cells.iter()).map(|c| c.changed_at.map_or(-1, |g| g as i64)),
+            ),
+            heat: universe
+                .heat_window()
+                .zip(universe.heat())
+                .map(|(window, values)| SavedHeat {
+                    window,
+                    values: to_runs(values.iter().copied()),
+                }),
+            generation_zero: generation_zero.map(Pattern::to_rle),
+            display,
+            camera,
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Self, SessionError> {
+        let text = std::fs::read_to_string(path)?;
+        toml::from_str(&text).map_err(SessionError::Format)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), SessionError> {
+        std::fs::write(path, toml::to_string(self).unwrap())?;
+        Ok(())
+    }
+
+    pub fn universe(&self) -> Result<Universe, SessionError> {
+        if self.width == 0 || self.height == 0 {
+            return Err(SessionError::EmptyBoard);
+        }
+        let mut universe = Universe::new(self.width, self.height, self.rule);
+        universe.topology = self.topology;
+        universe.set_generation(self.generation);
+        Pattern::parse_rle(&self.cells)?.place(&mut universe, 0, 0);
+        if !self.changed_at.is_empty() {
+            let changed_at = from_runs(&self.changed_at, universe.cells.len())
+                .ok_or(SessionError::Mismatch("cell ages"))?;
+            for (cell, changed_at) in universe.cells.iter_mut().zip(changed_at) {
+                cell.changed_at = u64::try_from(changed_at).ok();
+            }
+        }
+        if let Some(heat) = &self.heat {
+            let values = from_runs(&heat.values, universe.cells.len())
+                .ok_or(SessionError::Mismatch("heat map values"))?;
+            universe.set_heat_window(Some(heat.window));
+            if let Some(heat) = universe.heat_mut() {
+                heat.copy_from_slice(&values);
+            }
+        }
+        Ok(universe)
+    }
+
+    pub fn generation_zero(&self) -> Result<Option<Pattern>, SessionError> {
+        match &self.generation_zero {
+            Some(rle) => Ok(Some(Pattern::parse_rle(rle)?)),
+            None => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cell::CellState;
+
+    /// A board that has run for a while under a Generations rule, so it has
+    /// dying cells, ages and heat.
+    fn running_board() -> Universe {
+        let mut universe = Universe::new(12, 10, "B2/S/C4".parse().unwrap());
+        universe.topology = Topology::Torus;
+        universe.set_heat_window(Some(8));
+        for (x, y) in [(4, 4), (5, 4), (4, 5), (7, 6), (8, 6)] {
+            universe.set(x, y, CellState::Alive);
+        }
+        for _ in 0..6 {
+            universe.tick();
+        }
+        universe
+    }
+
+    fn round_trip(session: &Session) -> Session {
+        toml::from_str(&toml::to_string(session).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn sessions_restore_the_board() {
+        let original = running_board();
+        let mut start = Universe::new(12, 10, original.rule);
+        start.set(1, 1, CellState::Alive);
+        start.set(2, 1, CellState::Dying(2));
+        let generation_zero = Pattern::snapshot(&start);
+        let session = Session::capture(
+            &original,
+            Some(&generation_zero),
+            DisplayConfig::default(),
+            Camera::default(),
+            20,
+        );
+
+        let session = round_trip(&session);
+        let universe = session.universe().unwrap();
+        assert_eq!(universe.width(), original.width());
+        assert_eq!(universe.height(), original.height());
+        assert_eq!(universe.rule, original.rule);
+        assert_eq!(universe.topology, original.topology);
+        assert_eq!(universe.generation(), original.generation());
+        assert_eq!(universe.heat_window(), original.heat_window());
+        assert_eq!(universe.heat(), original.heat());
+        for (cell, expected) in universe.cells.iter().zip(&original.cells) {
+            assert_eq!(cell.state, expected.state, "({}, {})", cell.x, cell.y);
+            assert_eq!(
+                cell.changed_at, expected.changed_at,
+                "({}, {})",
+                cell.x, cell.y
+            );
+        }
+        assert_eq!(session.speed, 20);
+
+        let restored = session.generation_zero().unwrap().unwrap();
+        assert_eq!(restored.cells, generation_zero.cells);
+        assert_eq!(restored.dying, generation_zero.dying);
+    }
+
+    #[test]
+    fn per_cell_data_must_cover_the_board() {
+        let mut session = Session::capture(
+            &running_board(),
+            None,
+            DisplayConfig::default(),
+            Camera::default(),
+            12,
+        );
+        session.changed_at.pop();
+        assert!(matches!(
+            session.universe(),
+            Err(SessionError::Mismatch("cell ages"))
+        ));
+    }
+}
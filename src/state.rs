@@ -1,208 +1,866 @@
+use std::collections::BTreeMap;
+
 use egui_winit::winit;
 use winit::dpi::PhysicalPosition;
 use winit::window::Window;
 
-use crate::cell::{Cell, CellState};
+use crate::animation::{Animation, Crop};
+use crate::camera::Camera;
+use crate::cell::CellState;
+use crate::cli::GuiArgs;
+use crate::config::{Config, SimulationConfig, WindowConfig, LARGE_RANDOM_FILL};
 use crate::gpu::Gpu;
-use crate::gui::GuiCtx;
+use crate::gui::{Action, Confirm, ExportRegion, GuiCtx};
+use crate::history::History;
+use crate::keymap::{InputAction, KeyCombo, Keymap};
+use crate::minimap::Minimap;
+use crate::pattern::Pattern;
+use crate::session::{Session, SessionError};
+use crate::svg;
+use crate::tools::{self, Point, Run, Tool};
+use crate::universe::{Backend, Universe};
 
-// TODO: handle automatically
 pub const GRID_LINE_SIZE: usize = 100;
 pub const GRID_COLUMN_SIZE: usize = 100;
 pub const TICK_PER_SEC: u32 = 12;
+pub const WINDOW_TITLE: &str = "Conway's Game of Life";
+
+pub const INITIAL_CELL_GAP: f32 = 1.0 / 3.0;
+
+/// Window pixels moved per arrow key press.
+const PAN_STEP: f32 = 40.0;
+/// Zoom multiplier per mouse wheel notch.
+const ZOOM_STEP: f32 = 1.1;
+/// Zoom multiplier per zoom key press.
+const KEY_ZOOM_STEP: f32 = 1.25;
+/// How often the minimap is redrawn while it is open. It reads the whole
+/// board, which means a readback with the GPU backend.
+const MINIMAP_INTERVAL: std::time::Duration = std::time::Duration::from_millis(250);
 
-pub const INITIAL_SCALE_FACTOR: f32 = 10.0;
-pub const INITIAL_OFFSET: f32 = 5.0;
+/// A drag with a drawing tool, from button press to release.
+struct Stroke {
+    tool: Tool,
+    /// What the stroke turns cells into.
+    state: CellState,
+    start: Point,
+    last: Point,
+    /// Cells a shape tool will change when the button is released.
+    preview: Vec<Run>,
+}
 
 pub struct State {
     gpu: Gpu,
     window: Window,
     ctx: GuiCtx,
 
-    pub cells: [Cell; GRID_LINE_SIZE * GRID_COLUMN_SIZE],
+    pub universe: Universe,
+    /// Name of the pattern opened at startup, used to title exports.
+    pub pattern_name: Option<String>,
+    /// Write the settings back to the config file on exit.
+    pub save_config: bool,
+    generation_zero: Option<Pattern>,
+    camera: Camera,
+    backend: Backend,
+    keymap: Keymap,
+    /// Config file bindings, written back unchanged on exit.
+    keys: BTreeMap<InputAction, Vec<KeyCombo>>,
+    /// Advance one generation on the next frame while paused.
+    step_requested: bool,
+    /// The board changed since the session was last saved or loaded.
+    unsaved: bool,
+    /// The user confirmed quitting.
+    quit: bool,
+    /// The GPU backend has generations the CPU copy of the board lacks.
+    gpu_ahead: bool,
 
     mouse_pos: PhysicalPosition<f64>,
-    mouse_left_pressed: bool,
-    mouse_right_pressed: bool,
+    stroke: Option<Stroke>,
+    /// Cells drawn since the last frame, applied together before the next
+    /// generation so editing a running board doesn't stall on readbacks.
+    pending_edits: Vec<((u32, u32), CellState)>,
+    history: History,
+    mouse_middle_pressed: bool,
+    modifiers: winit::event::ModifiersState,
+    /// Cells copied from the selection, dead ones included.
+    clipboard: Option<Pattern>,
+    /// Corner the selection is being dragged from.
+    selection_anchor: Option<(u32, u32)>,
+    minimap_updated: Option<std::time::Instant>,
 
     gui_state: crate::gui::State,
 }
 
 impl State {
-    pub async fn new(window: Window, event_loop: &winit::event_loop::EventLoop<()>) -> Self {
-        let mut cells = [Cell::default(); GRID_LINE_SIZE * GRID_COLUMN_SIZE];
-        for i in 0..GRID_LINE_SIZE {
-            for j in 0..GRID_COLUMN_SIZE {
-                let idx = Self::cell_idx(i as u32, j as u32);
-                cells[idx].x = i as u32;
-                cells[idx].y = j as u32;
-            }
+    pub async fn new(
+        window: Window,
+        event_loop: &winit::event_loop::EventLoop<()>,
+        universe: Universe,
+        config: &Config,
+        args: &GuiArgs,
+    ) -> Result<Self, String> {
+        let mut gui_state = crate::gui::State::new(config);
+        gui_state.running = args.run;
+        if let Some(speed) = args.speed {
+            gui_state.ticks_per_sec = speed;
         }
 
-        let gpu = Gpu::new(&window, &cells[..], INITIAL_SCALE_FACTOR, INITIAL_OFFSET).await;
+        gui_state.show_rule(universe.rule);
+
+        let keymap = Keymap::new(&config.keys);
+        gui_state.shortcuts = keymap.cheat_sheet();
+
+        let camera = Camera::default();
+        let gpu = Gpu::new(&window, &universe, &gui_state.display(), &camera).await?;
         let ctx = GuiCtx::new(event_loop, gpu.device(), gpu.surface_config(), &window);
 
         log::info!("state initialized");
 
-        Self {
+        Ok(Self {
             gpu,
             window,
             ctx,
-            cells,
+            universe,
+            pattern_name: None,
+            save_config: true,
+            generation_zero: None,
+            camera,
+            backend: args.backend,
+            keymap,
+            keys: config.keys.clone(),
+            step_requested: false,
+            unsaved: false,
+            quit: false,
+            gpu_ahead: false,
             mouse_pos: PhysicalPosition::<f64>::new(0.0, 0.0),
-            mouse_left_pressed: false,
-            mouse_right_pressed: false,
-
-            gui_state: crate::gui::State {
-                running: false,
-                clear_color_r: 0.01,
-                clear_color_g: 0.01,
-                clear_color_b: 0.02,
-                cell_scale_factor: INITIAL_SCALE_FACTOR,
-                cell_offset: INITIAL_OFFSET,
+            stroke: None,
+            pending_edits: Vec::new(),
+            history: History::default(),
+            mouse_middle_pressed: false,
+            modifiers: Default::default(),
+            clipboard: None,
+            selection_anchor: None,
+            minimap_updated: None,
+
+            gui_state,
+        })
+    }
+
+    /// Current preferences, as they should be written to the config file.
+    pub fn config(&self) -> Config {
+        let size = self.window.inner_size();
+        let position = self.window.outer_position().ok();
+
+        // A fullscreen window's size says nothing about where the user wants
+        // the window next time, so keep whatever was saved before.
+        let window = match self.window.fullscreen() {
+            Some(_) => Config::load().window,
+            None => WindowConfig {
+                width: size.width,
+                height: size.height,
+                x: position.map(|p| p.x),
+                y: position.map(|p| p.y),
             },
+        };
+
+        Config {
+            display: self.gui_state.display(),
+            simulation: SimulationConfig {
+                rule: self.universe.rule,
+                speed: self.gui_state.ticks_per_sec,
+                edit_while_running: self.gui_state.edit_while_running,
+            },
+            window,
+            confirm: self.gui_state.confirm_config(),
+            keys: self.keys.clone(),
         }
     }
 
-    pub fn cell_idx(x: u32, y: u32) -> usize {
-        (x + y * GRID_LINE_SIZE as u32) as usize
+    fn reset_settings(&mut self) {
+        let config = Config::default();
+        self.gui_state.apply_config(&config);
+        self.universe.rule = config.simulation.rule;
+        self.gui_state.show_rule(self.universe.rule);
+        self.window.set_inner_size(winit::dpi::PhysicalSize::new(
+            config.window.width,
+            config.window.height,
+        ));
     }
 
-    pub fn tick_interval() -> std::time::Duration {
-        std::time::Duration::from_millis((1_000 / TICK_PER_SEC) as u64)
+    pub fn save_session(&self, path: &std::path::Path) -> Result<(), SessionError> {
+        Session::capture(
+            &self.universe,
+            self.generation_zero.as_ref(),
+            self.gui_state.display(),
+            self.camera,
+            self.gui_state.ticks_per_sec,
+        )
+        .save(path)
     }
 
-    pub fn window(&self) -> &Window {
-        &self.window
+    pub fn load_session(&mut self, path: &std::path::Path) -> Result<(), SessionError> {
+        let session = Session::load(path)?;
+        let universe = session.universe()?;
+        self.gpu
+            .check_board(universe.width(), universe.height(), &universe.rule)
+            .map_err(SessionError::Gpu)?;
+        self.universe = universe;
+        self.pending_edits.clear();
+        self.history.clear();
+        self.gui_state.show_rule(self.universe.rule);
+        self.gpu_ahead = false;
+        self.generation_zero = session.generation_zero()?;
+        self.gui_state.apply_display(&session.display);
+        self.camera = session.camera;
+        self.gui_state.ticks_per_sec = session.speed.max(1);
+        self.set_unsaved(false);
+        Ok(())
     }
 
-    pub fn resize(&mut self, dimensions: winit::dpi::PhysicalSize<u32>) {
-        self.gpu.resize(dimensions);
+    fn set_unsaved(&mut self, unsaved: bool) {
+        if self.unsaved != unsaved {
+            self.unsaved = unsaved;
+            self.window.set_title(&match unsaved {
+                true => format!("{WINDOW_TITLE} (unsaved)"),
+                false => WINDOW_TITLE.to_string(),
+            });
+        }
+    }
+
+    /// Whether the window can close now. With unsaved changes the user is
+    /// asked first, and [`State::quit_confirmed`] turns true if they agree.
+    pub fn request_quit(&mut self) -> bool {
+        if self.unsaved && self.gui_state.confirm_quit {
+            self.gui_state.confirm = Some(Confirm::Quit);
+            return false;
+        }
+        true
+    }
+
+    pub fn quit_confirmed(&self) -> bool {
+        self.quit
+    }
+
+    /// Clears the board, asking first if that would kill any cell.
+    fn request_clear(&mut self) {
+        self.sync_from_gpu();
+        if self.gui_state.confirm_clear && self.universe.bounding_box().is_some() {
+            self.gui_state.confirm = Some(Confirm::Clear);
+        } else {
+            self.clear();
+        }
+    }
+
+    fn clear(&mut self) {
+        self.pending_edits.clear();
+        self.sync_from_gpu();
+        self.history.begin();
+        for y in 0..self.universe.height() {
+            for x in 0..self.universe.width() {
+                self.history
+                    .set(&mut self.universe, (x, y), CellState::Dead);
+            }
+        }
+        self.universe.clear();
+        self.set_unsaved(true);
+    }
+
+    /// The selection, or the whole board without one, clipped to the board
+    /// in case it was made on a bigger one.
+    fn fill_area(&self) -> (u32, u32, u32, u32) {
+        let (width, height) = (self.universe.width(), self.universe.height());
+        let (x, y, w, h) = self.gui_state.selection.unwrap_or((0, 0, width, height));
+        let (x, y) = (x.min(width), y.min(height));
+        (x, y, w.min(width - x), h.min(height - y))
     }
 
-    fn tick(&mut self) {
-        macro_rules! valid_idx {
-            ($cond:expr, $x:expr, $y:expr) => {
-                if $cond {
-                    Some(Self::cell_idx($x, $y))
-                } else {
-                    None
+    /// Randomly fills the selection or the board, asking first if that
+    /// replaces a large area.
+    fn request_random_fill(&mut self) {
+        let (_, _, width, height) = self.fill_area();
+        if self.gui_state.confirm_random_fill && width as u64 * height as u64 > LARGE_RANDOM_FILL {
+            self.gui_state.confirm = Some(Confirm::RandomFill);
+        } else {
+            self.random_fill();
+        }
+    }
+
+    /// Brings each cell of the selection or the board to life with the
+    /// chosen probability and kills the rest, as one edit.
+    fn random_fill(&mut self) {
+        let (x0, y0, width, height) = self.fill_area();
+        let density = self.gui_state.fill_density;
+        self.begin_edit();
+        self.sync_from_gpu();
+        for y in y0..y0 + height {
+            for x in x0..x0 + width {
+                let state = match fastrand::u32(0..100) < density {
+                    true => CellState::Alive,
+                    false => CellState::Dead,
+                };
+                self.history.set(&mut self.universe, (x, y), state);
+            }
+        }
+        self.set_unsaved(true);
+    }
+
+    fn reset_to_generation_zero(&mut self) {
+        if let Some(pattern) = &self.generation_zero {
+            self.pending_edits.clear();
+            self.history.clear();
+            self.universe.clear();
+            self.universe.set_generation(0);
+            pattern.place(&mut self.universe, 0, 0);
+            self.gui_state.running = false;
+            self.set_unsaved(true);
+        }
+    }
+
+    /// Frames the live cells, or the whole board when it is empty.
+    pub fn fit_pattern(&mut self) {
+        let rect = self.universe.bounding_box().unwrap_or((
+            0,
+            0,
+            self.universe.width(),
+            self.universe.height(),
+        ));
+        let size = self.window.inner_size();
+        self.camera.fit(rect, (size.width, size.height));
+    }
+
+    /// Saves the current view as a PNG, `export_scale` times the window size.
+    pub fn screenshot(&mut self, path: &std::path::Path) -> Result<(), String> {
+        // Whatever changed this frame isn't on the GPU yet.
+        self.gpu
+            .update_cells(&mut self.universe, &self.gui_state.display());
+        let image = self.gpu.screenshot(
+            &self.camera,
+            self.gui_state.export_scale,
+            &self.gui_state.display(),
+        )?;
+        image.save_png(path).map_err(|e| e.to_string())
+    }
+
+    /// Simulates a copy of the board through the GUI's frame range and saves
+    /// it as an animation, leaving the board itself untouched. Ranges that
+    /// start in the past are replayed from generation 0.
+    pub fn export_animation(&self, path: &std::path::Path) -> Result<usize, String> {
+        let range = self.gui_state.animation_range;
+        let mut universe = if range.from >= self.universe.generation() {
+            self.universe.clone()
+        } else {
+            let pattern = self.generation_zero.as_ref().ok_or_else(|| {
+                format!(
+                    "generation {} has passed and there is no generation 0 snapshot",
+                    range.from
+                )
+            })?;
+            let mut universe = Universe::new(
+                self.universe.width(),
+                self.universe.height(),
+                self.universe.rule,
+            );
+            universe.topology = self.universe.topology;
+            pattern.place(&mut universe, 0, 0);
+            universe
+        };
+
+        let animation = Animation::record(&mut universe, range);
+        let size = self.window.inner_size();
+        let visible = self.camera.visible_cells(
+            (size.width, size.height),
+            universe.width(),
+            universe.height(),
+        );
+        let crop = match visible {
+            Some((x, y, width, height)) if self.gui_state.animation_crop_to_view => Crop {
+                x,
+                y,
+                width,
+                height,
+            },
+            _ => animation.fit_crop(),
+        };
+        animation
+            .save(
+                path,
+                crop,
+                &self.gui_state.animation_style(),
+                self.gui_state.animation_delay,
+            )
+            .map_err(|e| e.to_string())?;
+        Ok(animation.frame_count())
+    }
+
+    /// Saves the live cells of the GUI's export region as an SVG figure.
+    pub fn export_svg(&self, path: &std::path::Path) -> Result<(), std::io::Error> {
+        let (width, height) = (self.universe.width(), self.universe.height());
+        let board = (0, 0, width, height);
+        let size = self.window.inner_size();
+        let rect = match self.gui_state.svg_region {
+            ExportRegion::LiveCells => self.universe.bounding_box().unwrap_or(board),
+            ExportRegion::View => self
+                .camera
+                .visible_cells((size.width, size.height), width, height)
+                .unwrap_or(board),
+            ExportRegion::Board => board,
+        };
+        let mut pattern = Pattern::from_region(&self.universe, rect);
+        pattern.name = self.pattern_name.clone();
+        std::fs::write(path, svg::render(&pattern, &self.gui_state.svg_style()))
+    }
+
+    /// Brings the CPU board up to date after running on the GPU backend.
+    fn sync_from_gpu(&mut self) {
+        if self.gpu_ahead {
+            self.gpu.download_cells(&mut self.universe);
+            self.gpu_ahead = false;
+        }
+    }
+
+    fn handle_action(&mut self, action: Action) {
+        self.sync_from_gpu();
+        let path = std::path::PathBuf::from(&self.gui_state.session_path);
+        match action {
+            Action::ResetSettings => self.reset_settings(),
+            Action::ResetToGenerationZero => self.reset_to_generation_zero(),
+            Action::FitPattern => self.fit_pattern(),
+            Action::ToggleFullscreen => self.toggle_fullscreen(),
+            Action::SaveSession => {
+                self.gui_state.status = match self.save_session(&path) {
+                    Ok(()) => {
+                        self.set_unsaved(false);
+                        format!("Saved {}", path.display())
+                    }
+                    Err(e) => format!("Couldn't save: {e}"),
+                }
+            }
+            Action::LoadSession if self.unsaved && self.gui_state.confirm_load => {
+                self.gui_state.confirm = Some(Confirm::LoadSession)
+            }
+            Action::LoadSession | Action::Confirmed(Confirm::LoadSession) => {
+                self.gui_state.status = match self.load_session(&path) {
+                    Ok(()) => format!("Loaded {}", path.display()),
+                    Err(e) => format!("Couldn't load: {e}"),
+                }
+            }
+            Action::ExportAnimation => {
+                let path = std::path::PathBuf::from(&self.gui_state.animation_path);
+                self.gui_state.export_status = match self.export_animation(&path) {
+                    Ok(frames) => format!("Saved {frames} frames to {}", path.display()),
+                    Err(e) => format!("Couldn't save: {e}"),
+                }
+            }
+            Action::ExportSvg => {
+                let path = std::path::PathBuf::from(&self.gui_state.svg_path);
+                self.gui_state.export_status = match self.export_svg(&path) {
+                    Ok(()) => format!("Saved {}", path.display()),
+                    Err(e) => format!("Couldn't save: {e}"),
                 }
-            };
-        }
-
-        let cells = self.cells.map(|mut cell| {
-            let x = cell.x;
-            let y = cell.y;
-
-            let has_left = x > 0;
-            let has_top = y > 0;
-            let has_right = x < GRID_LINE_SIZE as u32 - 1;
-            let has_bottom = y < GRID_COLUMN_SIZE as u32 - 1;
-
-            let neighbors = [
-                valid_idx!(has_top, x, y - 1),
-                valid_idx!(has_bottom, x, y + 1),
-                valid_idx!(has_right, x + 1, y),
-                valid_idx!(has_left, x - 1, y),
-                valid_idx!(has_right && has_top, x + 1, y - 1),
-                valid_idx!(has_right && has_bottom, x + 1, y + 1),
-                valid_idx!(has_left && has_top, x - 1, y - 1),
-                valid_idx!(has_left && has_bottom, x - 1, y + 1),
-            ]
-            .iter()
-            .filter(|c| c.is_some() && self.cells[c.unwrap()].state.is_alive())
-            .fold(0, |acc, _| acc + 1);
-
-            match neighbors {
-                2..=3 if cell.state.is_alive() => (),
-                3 if !cell.state.is_alive() => cell.state = CellState::Alive,
-                _ => cell.state = CellState::Dead,
             }
+            Action::Screenshot => {
+                let path = std::path::PathBuf::from(&self.gui_state.export_path);
+                self.gui_state.export_status = match self.screenshot(&path) {
+                    Ok(()) => format!("Saved {}", path.display()),
+                    Err(e) => format!("Couldn't save: {e}"),
+                }
+            }
+            Action::Clear => self.request_clear(),
+            Action::Confirmed(Confirm::Clear) => self.clear(),
+            Action::RandomFill => self.request_random_fill(),
+            Action::Confirmed(Confirm::RandomFill) => self.random_fill(),
+            Action::ApplyRule => match self.gui_state.rule_text.parse().and_then(|rule| {
+                let (width, height) = (self.universe.width(), self.universe.height());
+                self.gpu.check_board(width, height, &rule).map(|_| rule)
+            }) {
+                Ok(rule) => {
+                    self.universe.rule = rule;
+                    self.gui_state.show_rule(rule);
+                    self.set_unsaved(true);
+                }
+                Err(e) => self.gui_state.rule_error = Some(e),
+            },
+            Action::Confirmed(Confirm::Quit) => self.quit = true,
+        }
+    }
 
-            cell
-        });
-        self.cells = cells;
+    pub fn tick_interval(&self) -> std::time::Duration {
+        std::time::Duration::from_millis((1_000 / self.gui_state.ticks_per_sec) as u64)
+    }
+
+    pub fn window(&self) -> &Window {
+        &self.window
+    }
+
+    /// Follows window size and DPI changes. The projection is rebuilt from
+    /// the surface size every frame, so only the surface and egui need updating.
+    pub fn resize(&mut self, dimensions: winit::dpi::PhysicalSize<u32>, scale_factor: f64) {
+        self.gpu.resize(dimensions);
+        self.ctx.resize(dimensions, scale_factor);
+    }
+
+    pub fn toggle_fullscreen(&mut self) {
+        let fullscreen = match self.window.fullscreen() {
+            Some(_) => None,
+            None => Some(winit::window::Fullscreen::Borderless(None)),
+        };
+        self.window.set_fullscreen(fullscreen);
     }
 
     pub fn update(&mut self) {
-        self.gpu.update_cells(
-            &self.cells,
-            self.gui_state.cell_scale_factor,
-            self.gui_state.cell_offset,
-        );
+        self.apply_pending_edits();
+        if let Some(action) = self.gui_state.requested.take() {
+            self.handle_action(action);
+        }
 
-        if self.gui_state.running {
-            let start = std::time::Instant::now();
+        let display = self.gui_state.display();
+        let heat_window = display.heat_map.then_some(display.heat_window);
+        if self.universe.heat_window() != heat_window {
+            self.sync_from_gpu();
+            self.universe.set_heat_window(heat_window);
+        }
 
-            self.tick();
+        match self.gpu_ahead {
+            true => self.gpu.update_display(&display),
+            false => self.gpu.update_cells(&mut self.universe, &display),
+        }
+        self.gpu.update_camera(&self.camera);
 
+        if self.gui_state.running {
+            let start = std::time::Instant::now();
+            self.step();
             let delta = start.elapsed();
-            if delta < State::tick_interval() {
-                std::thread::sleep(State::tick_interval() - delta);
+            if delta < self.tick_interval() {
+                std::thread::sleep(self.tick_interval() - delta);
             }
+        } else if std::mem::take(&mut self.step_requested) {
+            self.step();
+        } else {
+            self.sync_from_gpu();
         }
 
+        self.gui_state.generation = self.universe.generation();
+        self.refresh_minimap();
+        self.gui_state.camera = self.camera;
+        self.gui_state.cursor_cell = self.cell_under_cursor();
         let output = self.ctx.build_ui(&mut self.gui_state, &self.window);
+        self.camera = self.gui_state.camera;
+        self.gpu.render(&mut self.ctx, output);
+    }
 
-        let clear_color = wgpu::Color {
-            a: 1.0,
-            r: self.gui_state.clear_color_r,
-            g: self.gui_state.clear_color_g,
-            b: self.gui_state.clear_color_b,
-        };
+    /// Advances the board one generation on the selected backend.
+    fn step(&mut self) {
+        if self.universe.generation() == 0 {
+            self.generation_zero = Some(Pattern::snapshot(&self.universe));
+        }
+        match self.backend {
+            Backend::Cpu => self.universe.tick(),
+            Backend::Gpu => {
+                self.gpu.step_cells(&self.universe, 1);
+                self.universe.set_generation(self.universe.generation() + 1);
+                self.gpu_ahead = true;
+            }
+        }
+        self.set_unsaved(true);
+    }
 
-        self.gpu.render(&mut self.ctx, output, clear_color);
+    fn run_input_action(&mut self, action: InputAction) {
+        let size = self.window.inner_size();
+        let center = [size.width as f32 / 2.0, size.height as f32 / 2.0];
+        match action {
+            InputAction::Start => self.gui_state.running = true,
+            InputAction::Pause => self.gui_state.running = false,
+            InputAction::ToggleRunning => self.gui_state.running = !self.gui_state.running,
+            InputAction::Step if !self.gui_state.running => self.step_requested = true,
+            InputAction::Step => (),
+            InputAction::Clear => self.request_clear(),
+            InputAction::ResetToGenerationZero => {
+                self.gui_state.requested = Some(Action::ResetToGenerationZero)
+            }
+            InputAction::ZoomIn => self.camera.zoom_at(center, KEY_ZOOM_STEP),
+            InputAction::ZoomOut => self.camera.zoom_at(center, 1.0 / KEY_ZOOM_STEP),
+            InputAction::PanLeft => self.camera.pan([PAN_STEP, 0.0]),
+            InputAction::PanRight => self.camera.pan([-PAN_STEP, 0.0]),
+            InputAction::PanUp => self.camera.pan([0.0, PAN_STEP]),
+            InputAction::PanDown => self.camera.pan([0.0, -PAN_STEP]),
+            InputAction::FitPattern => self.fit_pattern(),
+            InputAction::ToggleFullscreen => self.toggle_fullscreen(),
+            InputAction::ClearSelection => self.gui_state.selection = None,
+            InputAction::Copy => self.copy_selection(),
+            InputAction::Paste if self.can_edit() => self.paste(),
+            InputAction::Paste => (),
+            InputAction::Undo if self.can_edit() => self.undo(),
+            InputAction::Redo if self.can_edit() => self.redo(),
+            InputAction::Undo | InputAction::Redo => (),
+            InputAction::ShowShortcuts => {
+                self.gui_state.shortcuts_open = !self.gui_state.shortcuts_open
+            }
+        }
     }
 
-    pub fn cell_index_from_pos(
-        pos: &PhysicalPosition<f64>,
-        scale_factor: f32,
-        offset: f32,
-    ) -> usize {
-        let gs = scale_factor as f64 + offset as f64;
-        let i = pos.x - (offset as f64) - (scale_factor as f64 / 2.0);
-        let j = pos.y - (offset as f64) - (scale_factor as f64 / 2.0);
+    fn refresh_minimap(&mut self) {
+        if !self.gui_state.minimap_open {
+            self.minimap_updated = None;
+            return;
+        }
+        if self
+            .minimap_updated
+            .is_some_and(|updated| updated.elapsed() < MINIMAP_INTERVAL)
+        {
+            return;
+        }
+        self.sync_from_gpu();
+        self.gui_state.minimap = Some(Minimap::new(&self.universe));
+        self.gui_state.minimap_changed = true;
+        self.minimap_updated = Some(std::time::Instant::now());
+    }
 
-        let i = (i / gs).floor() as usize;
-        let j = (j / gs).floor() as usize;
+    /// Cell under the mouse cursor, if any.
+    fn cell_under_cursor(&self) -> Option<(u32, u32)> {
+        self.camera.pick(
+            [self.mouse_pos.x as f32, self.mouse_pos.y as f32],
+            self.universe.width(),
+            self.universe.height(),
+        )
+    }
 
-        Self::cell_idx(i as u32, j as u32)
+    /// Cell under the mouse cursor, which may be off the board.
+    fn point_under_cursor(&self) -> Point {
+        let [x, y] = self
+            .camera
+            .screen_to_world([self.mouse_pos.x as f32, self.mouse_pos.y as f32]);
+        (x.floor() as i64, y.floor() as i64)
     }
 
-    // TODO: better input handling
-    pub fn input(&mut self, event: &winit::event::WindowEvent) {
-        use winit::event::{ElementState, KeyboardInput, VirtualKeyCode, WindowEvent};
+    /// Queues the cells of `points` that are on the board to be set to
+    /// `state` before the next generation.
+    fn apply(&mut self, points: &[Point], state: CellState) {
+        let (width, height) = (self.universe.width(), self.universe.height());
+        self.pending_edits
+            .extend(tools::clip(points, width, height).map(|cell| (cell, state)));
+    }
 
-        if self.ctx.on_event(event) {
+    fn apply_pending_edits(&mut self) {
+        if self.pending_edits.is_empty() {
             return;
         }
+        self.sync_from_gpu();
+        for (cell, state) in std::mem::take(&mut self.pending_edits) {
+            self.history.set(&mut self.universe, cell, state);
+        }
+        self.set_unsaved(true);
+    }
+
+    /// Applies the queued cells of the previous edit, so the next one is
+    /// undone on its own.
+    fn begin_edit(&mut self) {
+        self.apply_pending_edits();
+        self.history.begin();
+    }
+
+    fn undo(&mut self) {
+        self.apply_pending_edits();
+        self.sync_from_gpu();
+        if self.history.undo(&mut self.universe) {
+            self.set_unsaved(true);
+        }
+    }
 
-        if !self.gui_state.running && self.mouse_left_pressed || self.mouse_right_pressed {
-            let idx = Self::cell_index_from_pos(
-                &self.mouse_pos,
-                self.gui_state.cell_scale_factor,
-                self.gui_state.cell_offset,
-            );
-            if idx < self.cells.len() {
-                if self.mouse_left_pressed {
-                    self.cells[idx].state = CellState::Alive;
-                } else if self.mouse_right_pressed {
-                    self.cells[idx].state = CellState::Dead;
+    fn redo(&mut self) {
+        self.apply_pending_edits();
+        self.sync_from_gpu();
+        if self.history.redo(&mut self.universe) {
+            self.set_unsaved(true);
+        }
+    }
+
+    fn copy_selection(&mut self) {
+        if let Some(rect) = self.gui_state.selection {
+            self.sync_from_gpu();
+            self.clipboard = Some(Pattern::from_region(&self.universe, rect));
+        }
+    }
+
+    /// Queues the copied cells to replace the ones under them, with the top
+    /// left corner at the cursor, like any other edit.
+    fn paste(&mut self) {
+        let (Some(pattern), Some((x, y))) = (&self.clipboard, self.cell_under_cursor()) else {
+            return;
+        };
+        let rows = pattern.rows();
+        self.begin_edit();
+        let (width, height) = (self.universe.width(), self.universe.height());
+        for (dy, row) in rows.into_iter().enumerate() {
+            for (dx, state) in row.into_iter().enumerate() {
+                let (cx, cy) = (x + dx as u32, y + dy as u32);
+                if cx < width && cy < height {
+                    self.pending_edits
+                        .push(((cx, cy), CellState::from_index(state)));
                 }
             }
         }
+    }
+
+    /// Whether mouse presses on the board start drawing.
+    fn can_edit(&self) -> bool {
+        !self.gui_state.running || self.gui_state.edit_while_running
+    }
+
+    /// Starts a stroke with the selected tool under the cursor. `state` is
+    /// alive for the left button and dead for the right one.
+    fn begin_stroke(&mut self, state: CellState) {
+        self.begin_edit();
+        let tool = self.gui_state.tool;
+        let state = match tool {
+            Tool::Eraser => CellState::Dead,
+            _ => state,
+        };
+        let start = self.point_under_cursor();
+        let preview = match tool {
+            _ if tool.is_immediate() => {
+                self.apply(&[start], state);
+                Vec::new()
+            }
+            Tool::Fill => match self.cell_under_cursor() {
+                Some(cell) => {
+                    self.sync_from_gpu();
+                    let (width, height) = (self.universe.width(), self.universe.height());
+                    tools::runs(tools::clip(
+                        &tools::flood_fill(&self.universe, cell),
+                        width,
+                        height,
+                    ))
+                }
+                None => Vec::new(),
+            },
+            _ => self.shape(tool, start, start),
+        };
+        self.stroke = Some(Stroke {
+            tool,
+            state,
+            start,
+            last: start,
+            preview,
+        });
+        self.show_preview();
+    }
+
+    /// Follows the cursor: immediate tools draw a line from where the cursor
+    /// was, so fast strokes have no gaps, and shape tools update their preview.
+    fn continue_stroke(&mut self) {
+        let point = self.point_under_cursor();
+        let Some(stroke) = &self.stroke else {
+            return;
+        };
+        if point == stroke.last {
+            return;
+        }
+        let (tool, state, start, last) = (stroke.tool, stroke.state, stroke.start, stroke.last);
+        match tool {
+            _ if tool.is_immediate() => self.apply(&tools::line(last, point), state),
+            Tool::Fill => (),
+            _ => {
+                let preview = self.shape(tool, start, point);
+                if let Some(stroke) = &mut self.stroke {
+                    stroke.preview = preview;
+                }
+                self.show_preview();
+            }
+        }
+        if let Some(stroke) = &mut self.stroke {
+            stroke.last = point;
+        }
+    }
+
+    /// Applies the previewed shape, if any.
+    fn end_stroke(&mut self) {
+        if let Some(stroke) = self.stroke.take() {
+            let cells = stroke
+                .preview
+                .iter()
+                .flat_map(|&(x, y, length)| (x..x + length).map(move |x| ((x, y), stroke.state)));
+            self.pending_edits.extend(cells);
+            self.show_preview();
+        }
+    }
+
+    /// Runs of `tool`'s shape from `start` to `end`, clipped to the board.
+    fn shape(&self, tool: Tool, start: Point, end: Point) -> Vec<Run> {
+        let filled = self.gui_state.fill_shapes;
+        let board = (self.universe.width(), self.universe.height());
+        match tool {
+            Tool::Line => tools::runs(tools::clip(&tools::line(start, end), board.0, board.1)),
+            Tool::Rectangle => tools::rectangle(start, end, filled, board),
+            Tool::Ellipse => tools::ellipse(start, end, filled, board),
+            Tool::Freehand | Tool::Eraser | Tool::Fill => Vec::new(),
+        }
+    }
+
+    /// Hands the stroke's preview to the GUI, which draws it over the board.
+    fn show_preview(&mut self) {
+        match &self.stroke {
+            Some(stroke) => {
+                self.gui_state.preview = stroke.preview.clone();
+                self.gui_state.preview_erases = stroke.state == CellState::Dead;
+            }
+            None => self.gui_state.preview.clear(),
+        }
+    }
+
+    pub fn input(&mut self, event: &winit::event::WindowEvent) {
+        use winit::event::{ElementState, KeyboardInput, MouseScrollDelta, WindowEvent};
+
+        if self.ctx.on_event(event) {
+            return;
+        }
 
         match event {
-            WindowEvent::CursorMoved { position, .. } => self.mouse_pos = *position,
+            WindowEvent::CursorMoved { position, .. } => {
+                if self.mouse_middle_pressed {
+                    self.camera.pan([
+                        (position.x - self.mouse_pos.x) as f32,
+                        (position.y - self.mouse_pos.y) as f32,
+                    ]);
+                }
+                self.mouse_pos = *position;
+                self.continue_stroke();
+                if let (Some(anchor), Some(cell)) =
+                    (self.selection_anchor, self.cell_under_cursor())
+                {
+                    self.gui_state.selection = Some(rect_between(anchor, cell));
+                }
+            }
+            WindowEvent::ModifiersChanged(modifiers) => self.modifiers = *modifiers,
+            WindowEvent::MouseWheel { delta, .. } => {
+                let notches = match delta {
+                    MouseScrollDelta::LineDelta(_, y) => *y,
+                    MouseScrollDelta::PixelDelta(p) => p.y as f32 / 20.0,
+                };
+                let anchor = [self.mouse_pos.x as f32, self.mouse_pos.y as f32];
+                self.camera.zoom_at(anchor, ZOOM_STEP.powf(notches));
+            }
             WindowEvent::MouseInput { state, button, .. } => match button {
                 winit::event::MouseButton::Left => match state {
-                    ElementState::Pressed => self.mouse_left_pressed = true,
-                    ElementState::Released => self.mouse_left_pressed = false,
+                    // Shift-dragging selects instead of painting.
+                    ElementState::Pressed if self.modifiers.shift() => {
+                        self.selection_anchor = self.cell_under_cursor();
+                        self.gui_state.selection =
+                            self.selection_anchor.map(|cell| rect_between(cell, cell));
+                    }
+                    ElementState::Pressed if self.can_edit() => self.begin_stroke(CellState::Alive),
+                    ElementState::Pressed => (),
+                    ElementState::Released => {
+                        self.end_stroke();
+                        self.selection_anchor = None;
+                    }
                 },
                 winit::event::MouseButton::Right => match state {
-                    ElementState::Pressed => self.mouse_right_pressed = true,
-                    ElementState::Released => self.mouse_right_pressed = false,
+                    ElementState::Pressed if self.can_edit() => self.begin_stroke(CellState::Dead),
+                    ElementState::Pressed => (),
+                    ElementState::Released => self.end_stroke(),
+                },
+                winit::event::MouseButton::Middle => match state {
+                    ElementState::Pressed => self.mouse_middle_pressed = true,
+                    ElementState::Released => self.mouse_middle_pressed = false,
                 },
                 _ => (),
             },
@@ -210,24 +868,15 @@ impl State {
                 input:
                     KeyboardInput {
                         state: ElementState::Pressed,
-                        virtual_keycode,
+                        virtual_keycode: Some(key),
                         ..
                     },
                 ..
-            } => match virtual_keycode {
-                Some(k) if *k == VirtualKeyCode::R => {
-                    for cell in self.cells.iter_mut() {
-                        cell.state = CellState::Dead;
-                    }
-                }
-                Some(k) if *k == VirtualKeyCode::S && !self.gui_state.running => {
-                    self.gui_state.running = true
+            } if self.gui_state.confirm.is_none() => {
+                if let Some(action) = self.keymap.action(*key, self.modifiers) {
+                    self.run_input_action(action);
                 }
-                Some(k) if *k == VirtualKeyCode::P && self.gui_state.running => {
-                    self.gui_state.running = false
-                }
-                _ => (),
-            },
+            }
             _ => (),
         }
     }
@@ -235,7 +884,9 @@ impl State {
 
 macro_rules! alive_state_at {
     ($self:expr => $x:expr, $y:expr) => {
-        $self.cells[Self::cell_idx($x, $y)].state = CellState::Alive;
+        if $x < $self.universe.width() && $y < $self.universe.height() {
+            $self.universe.set($x, $y, CellState::Alive);
+        }
     };
 }
 
@@ -363,6 +1014,19 @@ impl State {
     }
 }
 
-pub fn init(window: Window, event_loop: &winit::event_loop::EventLoop<()>) -> State {
-    pollster::block_on(State::new(window, event_loop))
+pub fn init(
+    window: Window,
+    event_loop: &winit::event_loop::EventLoop<()>,
+    universe: Universe,
+    config: &Config,
+    args: &GuiArgs,
+) -> Result<State, String> {
+    pollster::block_on(State::new(window, event_loop, universe, config, args))
+}
+
+/// Rectangle `(x, y, width, height)` with `a` and `b` as opposite corners,
+/// both included.
+fn rect_between(a: (u32, u32), b: (u32, u32)) -> (u32, u32, u32, u32) {
+    let (x, y) = (a.0.min(b.0), a.1.min(b.1));
+    (x, y, a.0.max(b.0) - x + 1, a.1.max(b.1) - y + 1)
 }
@@ -0,0 +1,103 @@
+use std::fmt::Write as _;
+
+use crate::pattern::Pattern;
+use crate::theme::{Rgb, Theme};
+
+pub const DEFAULT_SVG_CELL_SIZE: u32 = 10;
+
+/// How cells are laid out and colored in an SVG document.
+#[derive(Clone, Copy, Debug)]
+pub struct SvgStyle {
+    /// Size of a cell in pixels. The document itself is in cell units, so
+    /// this only sets its default display size.
+    pub cell_size: u32,
+    /// Merge horizontal runs of live cells into single rects.
+    pub merge_runs: bool,
+    pub grid: bool,
+    pub alive: Rgb,
+    /// Fill behind the cells, or transparent when `None`.
+    pub background: Option<Rgb>,
+    pub grid_color: Rgb,
+}
+
+impl SvgStyle {
+    pub fn new(theme: &Theme) -> Self {
+        Self {
+            cell_size: DEFAULT_SVG_CELL_SIZE,
+            merge_runs: true,
+            grid: false,
+            alive: theme.alive,
+            background: Some(theme.background),
+            grid_color: theme.grid,
+        }
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Vector drawing of `pattern`'s `width` x `height` area, titled with its
+/// name. Coordinates are in cells, so figures scale without blurring.
+pub fn render(pattern: &Pattern, style: &SvgStyle) -> String {
+    let (width, height) = (pattern.width.max(1), pattern.height.max(1));
+    let mut svg = String::new();
+    writeln!(svg, r#"<?xml version="1.0" encoding="UTF-8"?>"#).unwrap();
+    writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}" viewBox="0 0 {width} {height}" shape-rendering="crispEdges">"#,
+        width * style.cell_size,
+        height * style.cell_size,
+    )
+    .unwrap();
+    if let Some(name) = &pattern.name {
+        writeln!(svg, "  <title>{}</title>", escape(name)).unwrap();
+    }
+    if let Some(background) = style.background {
+        writeln!(
+            svg,
+            r#"  <rect width="{width}" height="{height}" fill="{background}"/>"#
+        )
+        .unwrap();
+    }
+
+    let mut cells = pattern.cells.clone();
+    cells.sort_by_key(|&(x, y)| (y, x));
+    writeln!(svg, r#"  <g fill="{}">"#, style.alive).unwrap();
+    let mut cells = cells.into_iter().peekable();
+    while let Some((x, y)) = cells.next() {
+        let mut run = 1;
+        while style.merge_runs && cells.peek() == Some(&(x + run, y)) {
+            cells.next();
+            run += 1;
+        }
+        writeln!(
+            svg,
+            r#"    <rect x="{x}" y="{y}" width="{run}" height="1"/>"#
+        )
+        .unwrap();
+    }
+    writeln!(svg, "  </g>").unwrap();
+
+    if style.grid {
+        let mut path = String::new();
+        for x in 0..=width {
+            write!(path, "M{x} 0V{height}").unwrap();
+        }
+        for y in 0..=height {
+            write!(path, "M0 {y}H{width}").unwrap();
+        }
+        writeln!(
+            svg,
+            r#"  <path d="{path}" fill="none" stroke="{}" stroke-width="0.05"/>"#,
+            style.grid_color
+        )
+        .unwrap();
+    }
+
+    writeln!(svg, "</svg>").unwrap();
+    svg
+}
@@ -0,0 +1,182 @@
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+/// A color written as `RRGGBB` hex, with an optional leading `#`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Rgb(pub [u8; 3]);
+
+impl Rgb {
+    /// Channels converted from sRGB to linear light, for blending and for
+    /// render targets that encode to sRGB on write.
+    pub fn to_linear(self) -> [f32; 3] {
+        self.0.map(|c| {
+            let c = c as f32 / 255.0;
+            if c <= 0.04045 {
+                c / 12.92
+            } else {
+                ((c + 0.055) / 1.055).powf(2.4)
+            }
+        })
+    }
+}
+
+impl FromStr for Rgb {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let hex = s.trim().trim_start_matches('#');
+        let channel = |i: usize| {
+            hex.get(i..i + 2)
+                .and_then(|c| u8::from_str_radix(c, 16).ok())
+        };
+        match (hex.len(), channel(0), channel(2), channel(4)) {
+            (6, Some(r), Some(g), Some(b)) => Ok(Rgb([r, g, b])),
+            _ => Err(format!("invalid color '{s}': expected RRGGBB hex")),
+        }
+    }
+}
+
+impl fmt::Display for Rgb {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [r, g, b] = self.0;
+        write!(f, "#{r:02x}{g:02x}{b:02x}")
+    }
+}
+
+impl Serialize for Rgb {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Rgb {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// Colors used to draw the board, in sRGB.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Theme {
+    pub alive: Rgb,
+    /// Newborn cells when coloring by age, fading to `alive` as they get older.
+    pub young: Rgb,
+    /// First dying state of Generations rules, fading to `dead` through the
+    /// later ones.
+    pub dying: Rgb,
+    pub dead: Rgb,
+    pub grid: Rgb,
+    pub selection: Rgb,
+    /// Heat map overlay, blended over cells by how often they were alive.
+    pub heat: Rgb,
+    /// Shown between cells and around the board.
+    pub background: Rgb,
+}
+
+impl std::default::Default for Theme {
+    fn default() -> Self {
+        ThemePreset::Dark.theme()
+    }
+}
+
+/// What decides the color of a cell.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Coloring {
+    /// Alive or dead
+    #[default]
+    State,
+    /// Live cells go from the theme's young color to its alive color as they
+    /// age, so still lifes, oscillators and moving debris stand apart
+    Age,
+}
+
+#[derive(
+    Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum, serde::Serialize, serde::Deserialize,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum ThemePreset {
+    Dark,
+    /// Dark cells on a light background, for slides and print
+    Light,
+    HighContrast,
+    /// Okabe-Ito colors that stay distinct with common color vision deficiencies
+    Colorblind,
+}
+
+impl ThemePreset {
+    pub const ALL: [ThemePreset; 4] = [
+        ThemePreset::Dark,
+        ThemePreset::Light,
+        ThemePreset::HighContrast,
+        ThemePreset::Colorblind,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ThemePreset::Dark => "Dark",
+            ThemePreset::Light => "Light",
+            ThemePreset::HighContrast => "High contrast",
+            ThemePreset::Colorblind => "Colorblind-safe",
+        }
+    }
+
+    pub fn theme(self) -> Theme {
+        let theme = |alive, young, dying, dead, grid, selection, heat, background| Theme {
+            alive: Rgb(alive),
+            young: Rgb(young),
+            dying: Rgb(dying),
+            dead: Rgb(dead),
+            grid: Rgb(grid),
+            selection: Rgb(selection),
+            heat: Rgb(heat),
+            background: Rgb(background),
+        };
+        match self {
+            ThemePreset::Dark => theme(
+                [0xcb, 0xda, 0xe7],
+                [0xe8, 0x80, 0x5a],
+                [0x5a, 0x84, 0xc8],
+                [0x4a, 0x4a, 0x54],
+                [0x2c, 0x2c, 0x3a],
+                [0xe0, 0xb0, 0x40],
+                [0xff, 0x30, 0x20],
+                [0x19, 0x19, 0x27],
+            ),
+            ThemePreset::Light => theme(
+                [0x20, 0x28, 0x30],
+                [0xd0, 0x4a, 0x20],
+                [0x4a, 0x78, 0xc0],
+                [0xe4, 0xe4, 0xde],
+                [0xc4, 0xc4, 0xbc],
+                [0x2a, 0x7a, 0xe2],
+                [0xe0, 0x20, 0x10],
+                [0xf7, 0xf7, 0xf3],
+            ),
+            ThemePreset::HighContrast => theme(
+                [0xff, 0xff, 0xff],
+                [0xff, 0x40, 0x40],
+                [0x00, 0x90, 0xff],
+                [0x1a, 0x1a, 0x1a],
+                [0x70, 0x70, 0x70],
+                [0xff, 0xff, 0x00],
+                [0xff, 0x00, 0xff],
+                [0x00, 0x00, 0x00],
+            ),
+            ThemePreset::Colorblind => theme(
+                [0xe6, 0x9f, 0x00],
+                [0xcc, 0x79, 0xa7],
+                [0x00, 0x72, 0xb2],
+                [0x1f, 0x2d, 0x3d],
+                [0x56, 0xb4, 0xe9],
+                [0x00, 0x9e, 0x73],
+                [0xd5, 0x5e, 0x00],
+                [0x10, 0x18, 0x20],
+            ),
+        }
+    }
+}
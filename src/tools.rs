@@ -0,0 +1,327 @@
+use std::collections::VecDeque;
+
+use crate::universe::Universe;
+
+/// What dragging on the board does.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Tool {
+    Freehand,
+    Line,
+    Rectangle,
+    Ellipse,
+    Fill,
+    Eraser,
+}
+
+impl Tool {
+    pub const ALL: [Tool; 6] = [
+        Tool::Freehand,
+        Tool::Line,
+        Tool::Rectangle,
+        Tool::Ellipse,
+        Tool::Fill,
+        Tool::Eraser,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Tool::Freehand => "Freehand",
+            Tool::Line => "Line",
+            Tool::Rectangle => "Rectangle",
+            Tool::Ellipse => "Ellipse",
+            Tool::Fill => "Fill",
+            Tool::Eraser => "Eraser",
+        }
+    }
+
+    /// Whether strokes change the board as the mouse moves, rather than being
+    /// previewed and applied when the button is released.
+    pub fn is_immediate(self) -> bool {
+        matches!(self, Tool::Freehand | Tool::Eraser)
+    }
+}
+
+/// A cell position that may lie outside the board, so shapes can be dragged
+/// past its edges and clipped afterwards.
+pub type Point = (i64, i64);
+
+/// A horizontal run of cells `(x, y, length)` on the board.
+pub type Run = (u32, u32, u32);
+
+/// Cells on the segment from `a` to `b`, both included, with no gaps.
+pub fn line(a: Point, b: Point) -> Vec<Point> {
+    let (dx, dy) = ((b.0 - a.0).abs(), -(b.1 - a.1).abs());
+    let (sx, sy) = ((b.0 - a.0).signum(), (b.1 - a.1).signum());
+    let (mut x, mut y) = a;
+    let mut error = dx + dy;
+    let mut cells = vec![a];
+    while (x, y) != b {
+        let e2 = 2 * error;
+        if e2 >= dy {
+            error += dy;
+            x += sx;
+        }
+        if e2 <= dx {
+            error += dx;
+            y += sy;
+        }
+        cells.push((x, y));
+    }
+    cells
+}
+
+/// The part of row `y` from `x0` to `x1`, both included, on a `board` sized
+/// board.
+fn clip_run(y: i64, x0: i64, x1: i64, board: (u32, u32)) -> Option<Run> {
+    let (x0, x1) = (x0.max(0), x1.min(board.0 as i64 - 1));
+    let on_board = y >= 0 && y < board.1 as i64 && x0 <= x1;
+    on_board.then(|| (x0 as u32, y as u32, (x1 - x0 + 1) as u32))
+}
+
+/// Rows of `y0..=y1` on a `board` sized board.
+fn board_rows(y0: i64, y1: i64, board: (u32, u32)) -> std::ops::RangeInclusive<i64> {
+    y0.max(0)..=y1.min(board.1 as i64 - 1)
+}
+
+/// Rectangle with `a` and `b` as opposite corners, or just its border, as
+/// runs clipped to a `board` sized board.
+pub fn rectangle(a: Point, b: Point, filled: bool, board: (u32, u32)) -> Vec<Run> {
+    let (x0, x1) = (a.0.min(b.0), a.0.max(b.0));
+    let (y0, y1) = (a.1.min(b.1), a.1.max(b.1));
+    let mut runs = Vec::new();
+    for y in board_rows(y0, y1, board) {
+        if filled || y == y0 || y == y1 {
+            runs.extend(clip_run(y, x0, x1, board));
+        } else {
+            runs.extend(clip_run(y, x0, x0, board));
+            if x1 != x0 {
+                runs.extend(clip_run(y, x1, x1, board));
+            }
+        }
+    }
+    runs
+}
+
+/// Ellipse inscribed in the rectangle with `a` and `b` as opposite corners,
+/// or just its outline, as runs clipped to a `board` sized board.
+pub fn ellipse(a: Point, b: Point, filled: bool, board: (u32, u32)) -> Vec<Run> {
+    let (x0, x1) = (a.0.min(b.0), a.0.max(b.0));
+    let (y0, y1) = (a.1.min(b.1), a.1.max(b.1));
+    let center = ((x0 + x1) as f64 / 2.0, (y0 + y1) as f64 / 2.0);
+    let radius = ((x1 - x0 + 1) as f64 / 2.0, (y1 - y0 + 1) as f64 / 2.0);
+    let inside = |x: i64, y: i64| {
+        let dx = (x as f64 - center.0) / radius.0;
+        let dy = (y as f64 - center.1) / radius.1;
+        dx * dx + dy * dy <= 1.0
+    };
+    // First and last cell of row `y` inside the ellipse. The square root
+    // gives the ends up to rounding, which `inside` then settles.
+    let span = |y: i64| {
+        let dy = (y as f64 - center.1) / radius.1;
+        let half = radius.0 * (1.0 - dy * dy).max(0.0).sqrt();
+        let mut left = ((center.0 - half).ceil() as i64).max(x0);
+        let mut right = ((center.0 + half).floor() as i64).min(x1);
+        while left > x0 && inside(left - 1, y) {
+            left -= 1;
+        }
+        while left <= right && !inside(left, y) {
+            left += 1;
+        }
+        while right < x1 && inside(right + 1, y) {
+            right += 1;
+        }
+        while right >= left && !inside(right, y) {
+            right -= 1;
+        }
+        (left <= right).then_some((left, right))
+    };
+
+    let mut runs = Vec::new();
+    for y in board_rows(y0, y1, board) {
+        let Some((left, right)) = span(y) else {
+            continue;
+        };
+        if filled {
+            runs.extend(clip_run(y, left, right, board));
+            continue;
+        }
+        // Cells are on the outline unless all four neighbors are inside,
+        // which leaves the middle of the row under both neighboring rows.
+        let (mut inner_left, mut inner_right) = (left + 1, right - 1);
+        for row in [span(y - 1), span(y + 1)] {
+            let (l, r) = row.unwrap_or((right, left));
+            inner_left = inner_left.max(l);
+            inner_right = inner_right.min(r);
+        }
+        if inner_left > inner_right {
+            runs.extend(clip_run(y, left, right, board));
+        } else {
+            runs.extend(clip_run(y, left, inner_left - 1, board));
+            runs.extend(clip_run(y, inner_right + 1, right, board));
+        }
+    }
+    runs
+}
+
+/// The region of cells sharing the state of `start` and connected to it
+/// through edges, on the board itself (fills don't wrap around a torus).
+pub fn flood_fill(universe: &Universe, start: (u32, u32)) -> Vec<Point> {
+    let (width, height) = (universe.width(), universe.height());
+    let state = universe.get(start.0, start.1);
+    let mut seen = vec![false; universe.cells.len()];
+    let mut queue = VecDeque::from([start]);
+    seen[universe.cell_idx(start.0, start.1)] = true;
+
+    let mut cells = Vec::new();
+    while let Some((x, y)) = queue.pop_front() {
+        cells.push((x as i64, y as i64));
+        let neighbors = [
+            (x.wrapping_sub(1), y),
+            (x + 1, y),
+            (x, y.wrapping_sub(1)),
+            (x, y + 1),
+        ];
+        for (nx, ny) in neighbors {
+            if nx >= width || ny >= height {
+                continue;
+            }
+            let idx = universe.cell_idx(nx, ny);
+            if !seen[idx] && universe.get(nx, ny) == state {
+                seen[idx] = true;
+                queue.push_back((nx, ny));
+            }
+        }
+    }
+    cells
+}
+
+/// The cells of `points` that lie on a `width` x `height` board.
+pub fn clip(points: &[Point], width: u32, height: u32) -> impl Iterator<Item = (u32, u32)> + '_ {
+    points.iter().filter_map(move |&(x, y)| {
+        let on_board = x >= 0 && y >= 0 && x < width as i64 && y < height as i64;
+        on_board.then_some((x as u32, y as u32))
+    })
+}
+
+/// Horizontal runs covering `cells`, for drawing a preview with as few
+/// shapes as possible.
+pub fn runs(cells: impl Iterator<Item = (u32, u32)>) -> Vec<Run> {
+    let mut cells: Vec<_> = cells.map(|(x, y)| (y, x)).collect();
+    cells.sort_unstable();
+    cells.dedup();
+
+    let mut runs: Vec<Run> = Vec::new();
+    for (y, x) in cells {
+        match runs.last_mut() {
+            Some((rx, ry, len)) if *ry == y && *rx + *len == x => *len += 1,
+            _ => runs.push((x, y, 1)),
+        }
+    }
+    runs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cell::CellState;
+    use crate::universe::Topology;
+
+    /// Cells of `runs` in row order.
+    fn cells(runs: &[Run]) -> Vec<(u32, u32)> {
+        runs.iter()
+            .flat_map(|&(x, y, length)| (x..x + length).map(move |x| (x, y)))
+            .collect()
+    }
+
+    /// The ellipse tested cell by cell, clipped afterwards.
+    fn reference_ellipse(a: Point, b: Point, filled: bool, board: (u32, u32)) -> Vec<(u32, u32)> {
+        let (x0, x1) = (a.0.min(b.0), a.0.max(b.0));
+        let (y0, y1) = (a.1.min(b.1), a.1.max(b.1));
+        let center = ((x0 + x1) as f64 / 2.0, (y0 + y1) as f64 / 2.0);
+        let radius = ((x1 - x0 + 1) as f64 / 2.0, (y1 - y0 + 1) as f64 / 2.0);
+        let inside = |x: i64, y: i64| {
+            let dx = (x as f64 - center.0) / radius.0;
+            let dy = (y as f64 - center.1) / radius.1;
+            dx * dx + dy * dy <= 1.0
+        };
+        let mut points = Vec::new();
+        for y in y0..=y1 {
+            for x in x0..=x1 {
+                let edge = !inside(x - 1, y)
+                    || !inside(x + 1, y)
+                    || !inside(x, y - 1)
+                    || !inside(x, y + 1);
+                if inside(x, y) && (filled || edge) {
+                    points.push((x, y));
+                }
+            }
+        }
+        clip(&points, board.0, board.1).collect()
+    }
+
+    #[test]
+    fn ellipse_runs_match_the_cells_inside() {
+        let board = (20, 15);
+        for (w, h) in (0..24).flat_map(|w| (0..24).map(move |h| (w, h))) {
+            for a in [(-3, -2), (2, 3), (9, 8)] {
+                let b = (a.0 + w, a.1 + h);
+                for filled in [false, true] {
+                    let runs = ellipse(a, b, filled, board);
+                    assert_eq!(
+                        cells(&runs),
+                        reference_ellipse(a, b, filled, board),
+                        "{a:?} {b:?}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn shapes_are_clipped_before_they_are_built() {
+        let far = (-1_000_000_000, -1_000_000_000);
+        let runs = rectangle(far, (1_000_000_000, 1_000_000_000), true, (10, 10));
+        assert_eq!(runs, (0..10).map(|y| (0, y, 10)).collect::<Vec<_>>());
+        assert!(rectangle(far, (1_000_000_000, 1_000_000_000), false, (10, 10)).is_empty());
+        assert_eq!(
+            rectangle((-3, 2), (4, 4), false, (10, 10)),
+            [(0, 2, 5), (4, 3, 1), (0, 4, 5)]
+        );
+    }
+
+    #[test]
+    fn lines_have_no_gaps() {
+        let ends = [(0, 0), (7, 3), (-4, 9), (2, -6), (-5, -5), (0, 8), (3, 0)];
+        for &a in &ends {
+            for &b in &ends {
+                let cells = line(a, b);
+                assert_eq!((cells[0], cells[cells.len() - 1]), (a, b));
+                assert_eq!(
+                    cells.len() as i64,
+                    (b.0 - a.0).abs().max((b.1 - a.1).abs()) + 1
+                );
+                for pair in cells.windows(2) {
+                    let step = (pair[1].0 - pair[0].0, pair[1].1 - pair[0].1);
+                    assert!(step.0.abs() <= 1 && step.1.abs() <= 1 && step != (0, 0));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn flood_fill_stays_on_the_board() {
+        // A wall down column 2 splits the dead cells in two.
+        let mut universe = Universe::new(6, 4, Default::default());
+        universe.topology = Topology::Torus;
+        for y in 0..4 {
+            universe.set(2, y, CellState::Alive);
+        }
+
+        let mut left = flood_fill(&universe, (0, 0));
+        left.sort();
+        let expected: Vec<Point> = (0..2).flat_map(|x| (0..4).map(move |y| (x, y))).collect();
+        assert_eq!(left, expected);
+        assert_eq!(flood_fill(&universe, (5, 3)).len(), 12);
+        assert_eq!(flood_fill(&universe, (2, 1)).len(), 4);
+    }
+}
@@ -0,0 +1,485 @@
+use std::ops::Range;
+
+use crate::cell::{Cell, CellState};
+use crate::rule::{Rule, NEIGHBORS};
+
+#[derive(
+    Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum, serde::Serialize, serde::Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Topology {
+    /// Cells outside the board are always dead
+    Bounded,
+    /// Opposite edges are joined, so patterns wrap around
+    Torus,
+}
+
+/// Where generations are computed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum Backend {
+    Cpu,
+    /// WGSL compute shader, with the board kept in GPU memory
+    Gpu,
+}
+
+/// Moving average of how often each cell has been alive, updated by `tick`.
+#[derive(Clone)]
+struct HeatMap {
+    /// Roughly how many generations the average covers.
+    window: u32,
+    values: Vec<f32>,
+}
+
+/// Heat values this close to where they are heading snap to it, so settled
+/// regions stop changing and their rows stay clean.
+pub const HEAT_EPSILON: f32 = 1e-3;
+
+/// Next heat value of a cell, shared with the compute shader's formula.
+pub fn next_heat(heat: f32, alive: bool, window: u32) -> f32 {
+    let target = alive as u32 as f32;
+    let next = heat + (target - heat) * (1.0 / window as f32);
+    match (target - next).abs() < HEAT_EPSILON {
+        true => target,
+        false => next,
+    }
+}
+
+/// Live cells from column `a` to `b` of a row, given the row's prefix sums
+/// with a leading zero. Columns past the edges wrap around on a torus, as
+/// many times as needed, and are dead otherwise. The compute shader counts
+/// the same way.
+fn row_count(sums: &[u32], a: i64, b: i64, torus: bool) -> u32 {
+    let width = sums.len() as i64 - 1;
+    if !torus {
+        let (a, b) = (a.max(0), b.min(width - 1));
+        return sums[b as usize + 1] - sums[a as usize];
+    }
+    let len = b - a + 1;
+    let (laps, rest) = (len / width, len % width);
+    let start = a.rem_euclid(width);
+    let end = start + rest;
+    let partial = if end <= width {
+        sums[end as usize] - sums[start as usize]
+    } else {
+        sums[width as usize] - sums[start as usize] + sums[(end - width) as usize]
+    };
+    laps as u32 * sums[width as usize] + partial
+}
+
+/// The simulated board, independent of any window or GPU state.
+#[derive(Clone)]
+pub struct Universe {
+    width: u32,
+    height: u32,
+    generation: u64,
+    pub rule: Rule,
+    pub topology: Topology,
+    /// Writing cells directly bypasses dirty tracking, prefer `set`.
+    pub cells: Vec<Cell>,
+    /// Rows changed since the last `take_dirty_rows`, so the GPU copy of the
+    /// board can be patched instead of uploaded whole.
+    dirty_rows: Vec<bool>,
+    heat: Option<HeatMap>,
+}
+
+impl Universe {
+    /// An empty board. Both sides must be at least 1 cell long.
+    pub fn new(width: u32, height: u32, rule: Rule) -> Self {
+        assert!(width > 0 && height > 0, "empty {width}x{height} board");
+        let len = (width as usize)
+            .checked_mul(height as usize)
+            .expect("board too large");
+        let mut cells = vec![Cell::default(); len];
+        for (i, cell) in cells.iter_mut().enumerate() {
+            cell.x = (i % width as usize) as u32;
+            cell.y = (i / width as usize) as u32;
+        }
+
+        Self {
+            width,
+            height,
+            generation: 0,
+            rule,
+            topology: Topology::Bounded,
+            cells,
+            dirty_rows: vec![true; height as usize],
+            heat: None,
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    pub fn set_generation(&mut self, generation: u64) {
+        self.generation = generation;
+    }
+
+    pub fn cell_idx(&self, x: u32, y: u32) -> usize {
+        x as usize + y as usize * self.width as usize
+    }
+
+    pub fn get(&self, x: u32, y: u32) -> CellState {
+        self.cells[self.cell_idx(x, y)].state
+    }
+
+    pub fn set(&mut self, x: u32, y: u32, state: CellState) {
+        let idx = self.cell_idx(x, y);
+        if self.cells[idx].state != state {
+            self.cells[idx].state = state;
+            self.cells[idx].changed_at = Some(self.generation);
+            self.dirty_rows[y as usize] = true;
+        }
+    }
+
+    /// Kills every cell and forgets their ages.
+    pub fn clear(&mut self) {
+        for cell in self.cells.iter_mut() {
+            if cell.state != CellState::Dead || cell.changed_at.is_some() {
+                cell.state = CellState::Dead;
+                cell.changed_at = None;
+                self.dirty_rows[cell.y as usize] = true;
+            }
+        }
+    }
+
+    /// Returns the changed rows as sorted, non-overlapping ranges and marks
+    /// the whole board clean.
+    pub fn take_dirty_rows(&mut self) -> Vec<Range<u32>> {
+        let mut ranges: Vec<Range<u32>> = Vec::new();
+        for (y, dirty) in self.dirty_rows.iter_mut().enumerate() {
+            if !std::mem::take(dirty) {
+                continue;
+            }
+            let y = y as u32;
+            match ranges.last_mut() {
+                Some(last) if last.end == y => last.end = y + 1,
+                _ => ranges.push(y..y + 1),
+            }
+        }
+        ranges
+    }
+
+    /// Forgets pending changes, for when the cells were just read back from
+    /// the GPU and both copies already agree.
+    pub fn mark_clean(&mut self) {
+        self.dirty_rows.fill(false);
+    }
+
+    /// Starts, stops or resizes the heat map. `None` turns it off; turning it
+    /// on starts from a cold board.
+    pub fn set_heat_window(&mut self, window: Option<u32>) {
+        match (window, &mut self.heat) {
+            (None, _) => self.heat = None,
+            (Some(window), Some(heat)) => heat.window = window.max(1),
+            (Some(window), None) => {
+                self.heat = Some(HeatMap {
+                    window: window.max(1),
+                    values: vec![0.0; self.cells.len()],
+                });
+                self.dirty_rows.fill(true);
+            }
+        }
+    }
+
+    pub fn heat_window(&self) -> Option<u32> {
+        self.heat.as_ref().map(|heat| heat.window)
+    }
+
+    /// Heat of every cell, from 0 (never alive lately) to 1 (always alive),
+    /// when the heat map is on.
+    pub fn heat(&self) -> Option<&[f32]> {
+        self.heat.as_ref().map(|heat| heat.values.as_slice())
+    }
+
+    pub fn heat_mut(&mut self) -> Option<&mut [f32]> {
+        self.heat.as_mut().map(|heat| heat.values.as_mut_slice())
+    }
+
+    pub fn population(&self) -> usize {
+        self.cells.iter().filter(|c| c.state.is_alive()).count()
+    }
+
+    /// Smallest `(x, y, width, height)` rectangle holding every cell that
+    /// isn't dead, dying ones included.
+    pub fn bounding_box(&self) -> Option<(u32, u32, u32, u32)> {
+        let mut alive = self.cells.iter().filter(|c| c.state != CellState::Dead);
+        let first = alive.next()?;
+        let (mut min_x, mut min_y, mut max_x, mut max_y) = (first.x, first.y, first.x, first.y);
+        for cell in alive {
+            min_x = min_x.min(cell.x);
+            min_y = min_y.min(cell.y);
+            max_x = max_x.max(cell.x);
+            max_y = max_y.max(cell.y);
+        }
+        Some((min_x, min_y, max_x - min_x + 1, max_y - min_y + 1))
+    }
+
+    /// Hash of the cell states only, used to detect repeating generations.
+    pub fn state_hash(&self) -> u64 {
+        use std::hash::{Hash, Hasher};
+
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        for cell in self.cells.iter() {
+            cell.state.index().hash(&mut hasher);
+        }
+        hasher.finish()
+    }
+
+    /// Whether the cell `dx, dy` away from `x, y` is alive. Cells past the
+    /// edges wrap around on a torus and are dead otherwise.
+    fn alive_at(&self, x: u32, y: u32, (dx, dy): (i32, i32)) -> bool {
+        let (w, h) = (self.width as i64, self.height as i64);
+        let (mut nx, mut ny) = (x as i64 + dx as i64, y as i64 + dy as i64);
+        match self.topology {
+            Topology::Bounded if nx < 0 || ny < 0 || nx >= w || ny >= h => return false,
+            Topology::Bounded => (),
+            Topology::Torus => {
+                nx = nx.rem_euclid(w);
+                ny = ny.rem_euclid(h);
+            }
+        }
+        self.get(nx as u32, ny as u32).is_alive()
+    }
+
+    fn alive_neighbors(&self, x: u32, y: u32) -> usize {
+        NEIGHBORS
+            .iter()
+            .filter(|&&offset| self.alive_at(x, y, offset))
+            .count()
+    }
+
+    /// Which of the 8 nearest neighbors are alive, as an arrangement for
+    /// isotropic rules.
+    fn alive_arrangement(&self, x: u32, y: u32) -> u8 {
+        NEIGHBORS.iter().fold(0, |arrangement, &offset| {
+            arrangement << 1 | self.alive_at(x, y, offset) as u8
+        })
+    }
+
+    /// Live neighbors of every cell, in `cells` order, or for isotropic rules
+    /// their arrangements.
+    fn neighbor_counts(&self) -> Vec<usize> {
+        if self.rule.isotropic.is_some() {
+            return self
+                .cells
+                .iter()
+                .map(|cell| self.alive_arrangement(cell.x, cell.y) as usize)
+                .collect();
+        }
+        if self.rule.is_nearest() {
+            return self
+                .cells
+                .iter()
+                .map(|cell| self.alive_neighbors(cell.x, cell.y))
+                .collect();
+        }
+
+        // Larger than Life neighborhoods can hold hundreds of cells. Prefix
+        // sums along each row turn every row of a neighborhood into two
+        // lookups, whatever its width.
+        let (width, height) = (self.width as usize, self.height as usize);
+        let mut sums = vec![0u32; (width + 1) * height];
+        for (y, row) in sums.chunks_mut(width + 1).enumerate() {
+            for x in 0..width {
+                row[x + 1] = row[x] + self.cells[x + y * width].state.is_alive() as u32;
+            }
+        }
+
+        let torus = self.topology == Topology::Torus;
+        let range = self.rule.range as i64;
+        let half_widths: Vec<i64> = (0..=self.rule.range)
+            .map(|dy| self.rule.neighborhood.half_width(self.rule.range, dy) as i64)
+            .collect();
+        self.cells
+            .iter()
+            .map(|cell| {
+                let (x, y) = (cell.x as i64, cell.y as i64);
+                let mut count = 0;
+                for dy in -range..=range {
+                    let row = match (torus, y + dy) {
+                        (true, row) => row.rem_euclid(height as i64),
+                        (false, row) if row < 0 || row >= height as i64 => continue,
+                        (false, row) => row,
+                    } as usize;
+                    let half = half_widths[dy.unsigned_abs() as usize];
+                    let sums = &sums[row * (width + 1)..(row + 1) * (width + 1)];
+                    count += row_count(sums, x - half, x + half, torus);
+                }
+                if !self.rule.middle && cell.state.is_alive() {
+                    count -= 1;
+                }
+                count as usize
+            })
+            .collect()
+    }
+
+    pub fn tick(&mut self) {
+        let next: Vec<CellState> = self
+            .cells
+            .iter()
+            .zip(self.neighbor_counts())
+            .map(|(cell, neighbors)| self.rule.next_state(cell.state, neighbors))
+            .collect();
+
+        self.generation += 1;
+        for (cell, state) in self.cells.iter_mut().zip(next) {
+            if cell.state != state {
+                cell.state = state;
+                cell.changed_at = Some(self.generation);
+                self.dirty_rows[cell.y as usize] = true;
+            }
+        }
+        if let Some(heat) = &mut self.heat {
+            for (cell, value) in self.cells.iter().zip(heat.values.iter_mut()) {
+                let next = next_heat(*value, cell.state.is_alive(), heat.window);
+                if next != *value {
+                    *value = next;
+                    self.dirty_rows[cell.y as usize] = true;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GLIDER: [(u32, u32); 5] = [(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)];
+
+    fn board(width: u32, height: u32, rule: &str, cells: &[(u32, u32)]) -> Universe {
+        let mut universe = Universe::new(width, height, rule.parse().unwrap());
+        for &(x, y) in cells {
+            universe.set(x, y, CellState::Alive);
+        }
+        universe
+    }
+
+    fn alive(universe: &Universe) -> Vec<(u32, u32)> {
+        let mut cells: Vec<_> = universe
+            .cells
+            .iter()
+            .filter(|c| c.state.is_alive())
+            .map(|c| (c.x, c.y))
+            .collect();
+        cells.sort();
+        cells
+    }
+
+    /// `cells` moved by `(dx, dy)`, wrapping around a `width` by `height`
+    /// board.
+    fn shifted(
+        cells: &[(u32, u32)],
+        (dx, dy): (u32, u32),
+        (width, height): (u32, u32),
+    ) -> Vec<(u32, u32)> {
+        let mut cells: Vec<_> = cells
+            .iter()
+            .map(|&(x, y)| ((x + dx) % width, (y + dy) % height))
+            .collect();
+        cells.sort();
+        cells
+    }
+
+    fn rectangle(width: u32, height: u32) -> Vec<(u32, u32)> {
+        (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .collect()
+    }
+
+    #[test]
+    fn glider_moves_diagonally() {
+        let mut universe = board(10, 10, "B3/S23", &shifted(&GLIDER, (2, 2), (10, 10)));
+        for _ in 0..4 {
+            universe.tick();
+        }
+        assert_eq!(universe.generation(), 4);
+        assert_eq!(alive(&universe), shifted(&GLIDER, (3, 3), (10, 10)));
+    }
+
+    #[test]
+    fn glider_wraps_around_a_torus() {
+        let mut universe = board(8, 6, "B3/S23", &shifted(&GLIDER, (6, 4), (8, 6)));
+        universe.topology = Topology::Torus;
+        for _ in 0..4 {
+            universe.tick();
+        }
+        assert_eq!(alive(&universe), shifted(&GLIDER, (7, 5), (8, 6)));
+    }
+
+    #[test]
+    fn bounded_edges_are_dead() {
+        // A blinker along the top edge loses the cell that would be born
+        // above it and dies out.
+        let mut universe = board(5, 5, "B3/S23", &[(0, 0), (1, 0), (2, 0)]);
+        universe.tick();
+        assert_eq!(alive(&universe), [(1, 0), (1, 1)]);
+        universe.tick();
+        assert_eq!(alive(&universe), []);
+    }
+
+    #[test]
+    fn isotropic_rules_look_at_the_arrangement() {
+        // The middle cell has 2 live neighbors side by side, the `2a`
+        // arrangement, in the first board and on opposite corners in the
+        // second.
+        for (cells, survives) in [([(0, 0), (1, 0)], false), ([(0, 0), (2, 2)], true)] {
+            let mut universe = board(5, 5, "B3/S2-a3", &cells);
+            universe.set(1, 1, CellState::Alive);
+            universe.tick();
+            assert_eq!(universe.get(1, 1).is_alive(), survives, "{cells:?}");
+        }
+    }
+
+    const BOSCO: &str = "R5,C0,M1,S34..58,B34..45,NM";
+
+    #[test]
+    fn bosco_block_is_still() {
+        // Every cell of a 6x6 block sees all 36 of them, and a dead cell sees
+        // at most 5 columns or rows of it, 30 cells, too few to be born.
+        let block = shifted(&rectangle(6, 6), (7, 7), (20, 20));
+        let mut universe = board(20, 20, BOSCO, &block);
+        universe.tick();
+        assert_eq!(alive(&universe), block);
+    }
+
+    #[test]
+    fn bosco_rectangle_oscillates() {
+        // The end rows of a 5x7 rectangle see 6 of its rows, 30 cells, and
+        // die, while the dead cells beside its middle 5 rows see 35 and are
+        // born, turning it into a 7x5 rectangle and back.
+        for topology in [Topology::Bounded, Topology::Torus] {
+            // On the torus the rectangles straddle the corner.
+            let offset = match topology {
+                Topology::Bounded => (7, 6),
+                Topology::Torus => (18, 17),
+            };
+            let tall = shifted(&rectangle(5, 7), offset, (20, 20));
+            let wide = shifted(&rectangle(7, 5), (offset.0 + 19, offset.1 + 1), (20, 20));
+            let mut universe = board(20, 20, BOSCO, &tall);
+            universe.topology = topology;
+            universe.tick();
+            assert_eq!(alive(&universe), wide, "{topology:?}");
+            universe.tick();
+            assert_eq!(alive(&universe), tall, "{topology:?}");
+        }
+    }
+
+    #[test]
+    fn row_count_wraps_on_a_torus() {
+        let sums = [0, 1, 1, 2, 3];
+        assert_eq!(row_count(&sums, -1, 1, false), 1);
+        assert_eq!(row_count(&sums, -1, 1, true), 2);
+        assert_eq!(row_count(&sums, 2, 5, true), 3);
+        assert_eq!(row_count(&sums, -5, 5, true), 8);
+    }
+}